@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::Deserialize;
+use tokio::fs as tokio_fs;
+
+use crate::client::RequestHeader;
+use crate::error::Result;
+use crate::general::{next_download_id, Download, DownloadError, Image};
+use crate::store::{read_records, write_records, Record};
+
+const EXTENSIONS: [&str; 4] = ["jpg", "png", "webp", "gif"];
+
+/// Picks `<itunes:image href>` over `<image><url>`, as it is usually the larger picture.
+pub fn image_url(itunes_image: Option<&Image>, image: Option<&Image>) -> Option<String> {
+    itunes_image.and_then(|image| image.href.clone())
+        .or_else(|| image.and_then(|image| image.url.clone()))
+        .filter(|url| !url.trim().is_empty())
+}
+
+/// The picture saved for `stem`, whichever extension it was given.
+pub fn find_artwork(stem: &Path) -> Option<PathBuf> {
+    EXTENSIONS.iter()
+        .map(|ext| artwork_path(stem, ext))
+        .find(|path| path.exists())
+}
+
+/// The stem an episode's picture is saved under: the audio file without its extension.
+pub fn episode_stem(audio: &Path) -> PathBuf {
+    audio.with_extension("")
+}
+
+/// `<stem>.<ext>`. Titles like `Ep. 12` contain dots, so the extension is appended rather
+/// than set with `with_extension`, which would cut the name at the last dot.
+fn artwork_path(stem: &Path, ext: &str) -> PathBuf {
+    let mut name = stem.as_os_str().to_os_string();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+/// Artwork that has been saved, kept in `artwork.xml` so it is only fetched again when the
+/// URL or the server's ETag changes.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SavedArtwork {
+    /// Path of the file without its extension, e.g. `<save-folder>/cover`.
+    pub stem: PathBuf,
+    pub url: String,
+    #[serde(default)]
+    pub etag: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SavedArtworkList {
+    #[serde(rename = "artwork", default)]
+    artwork: Vec<SavedArtwork>,
+}
+
+pub fn load_artwork(config_path: &Path) -> Result<Vec<SavedArtwork>> {
+    Ok(read_records::<SavedArtworkList>(&config_path.join("artwork.xml"))?.artwork)
+}
+
+pub fn save_artwork(config_path: &Path, artwork: &[SavedArtwork]) -> Result<()> {
+    let records: Vec<Record> = artwork.iter()
+        .map(|saved| {
+            let mut record = vec![
+                ("stem", saved.stem.to_string_lossy().into_owned()),
+                ("url", saved.url.clone()),
+            ];
+            if let Some(etag) = &saved.etag {
+                record.push(("etag", etag.clone()));
+            }
+            record
+        })
+        .collect();
+    write_records(&config_path.join("artwork.xml"), "artwork-list", "artwork", &records)
+}
+
+/// An episode that is tagged and handed to its hooks once its picture has been fetched.
+#[derive(Clone, Debug)]
+pub struct AfterArtwork {
+    pub episode: String,
+    pub path: PathBuf,
+}
+
+/// A picture to fetch unless the copy on disk is still current.
+#[derive(Clone, Debug)]
+pub struct ArtworkJob {
+    pub url: String,
+    pub stem: PathBuf,
+    pub feed_name: Option<String>,
+    /// Sent as `If-None-Match` when the URL hasn't changed.
+    pub etag: Option<String>,
+    pub after: Option<AfterArtwork>,
+}
+
+impl ArtworkJob {
+    /// Returns `None` when the picture at `stem` was saved from the same URL and the server
+    /// gave no ETag to check it against.
+    pub fn new(url: String, stem: PathBuf, feed_name: Option<String>, saved: &[SavedArtwork]) -> Option<ArtworkJob> {
+        let previous = saved.iter()
+            .find(|saved| saved.stem == stem && saved.url == url)
+            .filter(|_| find_artwork(&stem).is_some());
+        match previous {
+            Some(SavedArtwork { etag: None, .. }) => None,
+            Some(SavedArtwork { etag, .. }) => Some(ArtworkJob { url, stem, feed_name, etag: etag.clone(), after: None }),
+            None => Some(ArtworkJob { url, stem, feed_name, etag: None, after: None }),
+        }
+    }
+
+    /// Queues the picture like any other download, so it shares their limits and retries.
+    /// It is written to `<stem>.part` and only named once its type is known.
+    pub fn into_download(self, mut headers: Vec<RequestHeader>) -> Download {
+        if let Some(etag) = &self.etag {
+            headers.push(RequestHeader { name: String::from("If-None-Match"), value: etag.clone() });
+        }
+        Download {
+            id: next_download_id(),
+            url: self.url.clone(),
+            path: self.stem.clone(),
+            headers,
+            feed_name: self.feed_name.clone(),
+            artwork: Some(self),
+            ..Default::default()
+        }
+    }
+}
+
+/// Chooses the extension from the `Content-Type`, then from the URL, then falls back to `jpg`.
+fn extension(content_type: Option<&str>, url: &str) -> &'static str {
+    let from_type = content_type.and_then(|content_type| match content_type.split(';').next()?.trim() {
+        "image/png" => Some("png"),
+        "image/webp" => Some("webp"),
+        "image/gif" => Some("gif"),
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        _ => None,
+    });
+    let path = url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    from_type
+        .or_else(|| EXTENSIONS.iter().copied().find(|ext| path.ends_with(&format!(".{}", ext))))
+        .unwrap_or("jpg")
+}
+
+/// Names the downloaded picture after its type and removes the copies saved under other
+/// extensions.
+pub(crate) async fn finish_artwork(job: &ArtworkJob, part: &Path, content_type: Option<&str>, etag: Option<String>)
+        -> std::result::Result<SavedArtwork, DownloadError> {
+    let ext = extension(content_type, &job.url);
+    let path = artwork_path(&job.stem, ext);
+    tokio_fs::rename(part, &path).await.map_err(|e| DownloadError::from_io(&e))?;
+    for old in EXTENSIONS.iter().filter(|old| **old != ext) {
+        let _ = tokio_fs::remove_file(artwork_path(&job.stem, old)).await;
+    }
+    info!("Saved artwork {}", path.to_string_lossy());
+    Ok(SavedArtwork { stem: job.stem.clone(), url: job.url.clone(), etag })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dotted_titles_keep_their_whole_name() {
+        let stem = episode_stem(Path::new("/podcasts/Show/Ep. 12 Foo.mp3"));
+        assert_eq!(stem, Path::new("/podcasts/Show/Ep. 12 Foo"));
+        assert_eq!(artwork_path(&stem, "jpg"), Path::new("/podcasts/Show/Ep. 12 Foo.jpg"));
+        let stem = episode_stem(Path::new("Show 1.5.mp3"));
+        assert_eq!(artwork_path(&stem, "png"), Path::new("Show 1.5.png"));
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::{HomilyError, Result};
+use crate::general::{Downloadable, Episode, Styled};
+use crate::transcript::format_time;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub start: Duration,
+    pub title: String,
+    pub url: Option<String>,
+}
+
+impl Display for Chapter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:>8} {}", format_time(self.start), self.title)?;
+        if let Some(url) = &self.url {
+            write!(f, " <{}>", url)?;
+        }
+        Ok(())
+    }
+}
+
+impl Styled for Chapter {}
+
+#[derive(Deserialize)]
+struct JsonChapters {
+    chapters: Vec<JsonChapter>,
+}
+
+#[derive(Deserialize)]
+struct JsonChapter {
+    #[serde(rename = "startTime")]
+    start_time: f64,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    /// `false` for chapters that only carry artwork and shouldn't be listed.
+    #[serde(default = "listed")]
+    toc: bool,
+}
+
+fn listed() -> bool {
+    true
+}
+
+/// Reads the Podcasting 2.0 JSON chapters format.
+pub fn parse_json(text: &str) -> std::result::Result<Vec<Chapter>, serde_json::Error> {
+    let chapters: JsonChapters = serde_json::from_str(text)?;
+    Ok(chapters.chapters.into_iter()
+        .filter(|chapter| chapter.toc)
+        // A start time past anything a Duration can hold is a broken file; skip that chapter.
+        .filter_map(|chapter| Some(Chapter {
+            start: Duration::try_from_secs_f64(chapter.start_time.max(0.0)).ok()?,
+            title: chapter.title.unwrap_or_default(),
+            url: chapter.url,
+        }))
+        .collect())
+}
+
+/// Reads the CHAP frames of an MP3 file, in the order given by its table of contents when
+/// there is one.
+pub fn read_id3(path: &Path) -> Result<Vec<Chapter>> {
+    let tag = match id3::no_tag_ok(id3::Tag::read_from_path(path)).map_err(|e| HomilyError::tag(path, e))? {
+        Some(tag) => tag,
+        None => return Ok(vec![]),
+    };
+    let mut chapters: Vec<(&str, Chapter)> = tag.chapters()
+        .map(|chapter| (chapter.element_id.as_str(), Chapter {
+            start: Duration::from_millis(chapter.start_time as u64),
+            title: chapter.frames.iter()
+                .find(|frame| frame.id() == "TIT2")
+                .and_then(|frame| frame.content().text())
+                .unwrap_or(&chapter.element_id)
+                .to_string(),
+            url: chapter.frames.iter()
+                .find_map(|frame| frame.content().extended_link().map(|link| link.link.clone())
+                    .or_else(|| frame.content().link().map(String::from))),
+        }))
+        .collect();
+    let order: Vec<&str> = tag.tables_of_contents()
+        .find(|toc| toc.top_level)
+        .map(|toc| toc.elements.iter().map(String::as_str).collect())
+        .unwrap_or_default();
+    chapters.sort_by_key(|(id, chapter)| (order.iter().position(|element| element == id).unwrap_or(usize::MAX), chapter.start));
+    Ok(chapters.into_iter().map(|(_, chapter)| chapter).collect())
+}
+
+/// Chapters of a downloaded episode, from its `.chapters.json` file if that was downloaded
+/// and otherwise from the audio file itself.
+pub fn load_chapters(ep: &Episode) -> Result<Vec<Chapter>> {
+    let json_path = ep.chapters_path();
+    if json_path.exists() {
+        let text = fs::read_to_string(&json_path).map_err(|e| HomilyError::io(&json_path, e))?;
+        return parse_json(&text).map_err(|e| HomilyError::json(&json_path, e));
+    }
+    let audio_path = ep.save_path();
+    let audio_path = Path::new(&audio_path);
+    if audio_path.exists() {
+        read_id3(audio_path)
+    } else {
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_hidden_and_broken_chapters() {
+        let chapters = parse_json(r#"{"version": "1.2.0", "chapters": [
+            {"startTime": 0, "title": "Intro"},
+            {"startTime": 30.5, "title": "Hidden", "toc": false},
+            {"startTime": 1e300, "title": "Broken"},
+            {"startTime": 90, "title": "Main", "url": "http://example.com"}]}"#).unwrap();
+        let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Intro", "Main"]);
+        assert_eq!(chapters[1].start, Duration::from_secs(90));
+        assert_eq!(chapters[1].url.as_deref(), Some("http://example.com"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::Path;
+
+use quick_xml::de::from_str;
+use serde::Deserialize;
+
+use crate::client::ClientCertificate;
+use crate::error::{HomilyError, Result};
+use crate::player::PlayerMode;
+use crate::playlist::PlaylistFormat;
+use crate::ratelimit::{parse_rate, RateWindow};
+
+/// Settings read from `config.xml` in the homily folder. Every element is optional.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    /// Maximum number of downloads, feed refreshes included, that run at the same time.
+    #[serde(rename = "max-downloads")]
+    pub max_downloads: usize,
+    /// Maximum number of simultaneous downloads from a single host.
+    #[serde(rename = "max-downloads-per-host")]
+    pub max_downloads_per_host: usize,
+    /// Combined bandwidth for all downloads, e.g. `500K`. Unlimited when absent or `0`.
+    #[serde(rename = "rate-limit", deserialize_with = "parse_rate")]
+    pub rate_limit: Option<u64>,
+    /// Bandwidth for each single download.
+    #[serde(rename = "download-rate-limit", deserialize_with = "parse_rate")]
+    pub download_rate_limit: Option<u64>,
+    /// Times of day with a different global limit, as
+    /// `<rate-schedule from="23:00" to="06:00" limit="0"/>`.
+    #[serde(rename = "rate-schedule")]
+    pub rate_schedule: Vec<RateWindow>,
+    /// How many times a download is retried after a network error, a timeout, or a 5xx or 429 response.
+    #[serde(rename = "max-retries")]
+    pub max_retries: u32,
+    /// Sent with every request unless a feed sets its own.
+    #[serde(rename = "user-agent")]
+    pub user_agent: String,
+    /// Proxy for all requests, e.g. `http://proxy:3128` or `socks5://127.0.0.1:1080`.
+    pub proxy: Option<String>,
+    /// Seconds to wait for a connection. `0` waits forever.
+    #[serde(rename = "connect-timeout")]
+    pub connect_timeout: u64,
+    /// Seconds to wait for the next piece of a response. `0` waits forever.
+    #[serde(rename = "read-timeout")]
+    pub read_timeout: u64,
+    /// Shows credentials, tokens and cookies instead of masking them.
+    #[serde(rename = "reveal-secrets")]
+    pub reveal_secrets: bool,
+    /// PEM files with CA certificates to trust besides the system ones.
+    #[serde(rename = "ca-certificate")]
+    pub ca_certificates: Vec<String>,
+    #[serde(rename = "client-certificate")]
+    pub client_certificates: Vec<ClientCertificate>,
+    /// Commands run after every episode download, e.g. `<hook>rsync "$1" nas:/podcasts/</hook>`.
+    /// The file is passed as `$1` and the episode details as `HOMILY_*` variables.
+    #[serde(rename = "hook")]
+    pub hooks: Vec<String>,
+    /// Writes the feed, title, date, numbers, description and cover into downloaded MP3 and
+    /// M4A files. Feeds can override this with `<tag>`.
+    #[serde(rename = "tag-episodes")]
+    pub tag_episodes: bool,
+    /// Saves each episode's own picture next to its audio file, when it has one.
+    #[serde(rename = "episode-artwork")]
+    pub episode_artwork: bool,
+    /// Downloads `<podcast:transcript>` files next to each episode.
+    #[serde(rename = "download-transcripts")]
+    pub download_transcripts: bool,
+    /// Downloads `<podcast:chapters>` files next to each episode as `.chapters.json`.
+    #[serde(rename = "download-chapters")]
+    pub download_chapters: bool,
+    /// Command that plays an episode, e.g. `mpv --start={start} {file}`. `{file}` is the
+    /// downloaded file or, when there is none, the URL to stream; `{url}`, `{title}` and
+    /// `{start}` (in seconds) are also replaced.
+    pub player: Option<String>,
+    /// `terminal` hands the terminal to the player until it exits; `detached` runs it in the background.
+    #[serde(rename = "player-mode")]
+    pub player_mode: PlayerMode,
+    /// Percentage of an episode after which it counts as played, when the player reports
+    /// its position (mpv does).
+    #[serde(rename = "played-threshold")]
+    pub played_threshold: u8,
+    /// Where the export key writes playlists, with file paths relative to it. Defaults to
+    /// `playlists` in the homily folder.
+    #[serde(rename = "playlist-folder")]
+    pub playlist_folder: Option<String>,
+    /// `m3u8` or `pls`.
+    #[serde(rename = "playlist-format")]
+    pub playlist_format: PlaylistFormat,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_downloads: 8,
+            max_downloads_per_host: 2,
+            rate_limit: None,
+            download_rate_limit: None,
+            rate_schedule: vec![],
+            max_retries: 5,
+            user_agent: format!("homily/{}", env!("CARGO_PKG_VERSION")),
+            proxy: None,
+            connect_timeout: 30,
+            read_timeout: 60,
+            reveal_secrets: false,
+            ca_certificates: vec![],
+            client_certificates: vec![],
+            hooks: vec![],
+            tag_episodes: false,
+            episode_artwork: false,
+            download_transcripts: false,
+            download_chapters: false,
+            player: None,
+            player_mode: PlayerMode::Terminal,
+            played_threshold: 90,
+            playlist_folder: None,
+            playlist_format: PlaylistFormat::M3u8,
+        }
+    }
+}
+
+impl Config {
+    /// Reads `config.xml`, falling back to the defaults when there is none.
+    pub fn load(config_path: &Path) -> Result<Config> {
+        let path = config_path.join("config.xml");
+        match fs::read_to_string(&path) {
+            Ok(text) => from_str(&text).map_err(|e| HomilyError::parse(&path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(HomilyError::io(&path, e)),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use quick_xml::DeError;
+
+/// Everything that can go wrong in homily outside of a single download, which has its
+/// own `DownloadError`.
+#[derive(Debug)]
+pub enum HomilyError {
+    Io { path: PathBuf, source: std::io::Error },
+    Http(reqwest::Error),
+    Parse { path: PathBuf, source: DeError },
+    Json { path: PathBuf, source: serde_json::Error },
+    Config(String),
+    Tag { path: PathBuf, message: String },
+    /// The runtime or the logger couldn't be set up.
+    Startup(String),
+}
+
+pub type Result<T> = std::result::Result<T, HomilyError>;
+
+impl HomilyError {
+    pub fn io(path: &Path, source: std::io::Error) -> HomilyError {
+        HomilyError::Io { path: path.to_path_buf(), source }
+    }
+
+    pub fn parse(path: &Path, source: DeError) -> HomilyError {
+        HomilyError::Parse { path: path.to_path_buf(), source }
+    }
+
+    pub fn json(path: &Path, source: serde_json::Error) -> HomilyError {
+        HomilyError::Json { path: path.to_path_buf(), source }
+    }
+
+    pub fn tag(path: &Path, message: impl Display) -> HomilyError {
+        HomilyError::Tag { path: path.to_path_buf(), message: message.to_string() }
+    }
+}
+
+impl Display for HomilyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HomilyError::Io { path, source } => write!(f, "{}: {}", path.to_string_lossy(), source),
+            HomilyError::Http(e) => write!(f, "HTTP error: {}", e),
+            HomilyError::Parse { path, source } => write!(f, "couldn't parse {}: {}", path.to_string_lossy(), source),
+            HomilyError::Json { path, source } => write!(f, "couldn't parse {}: {}", path.to_string_lossy(), source),
+            HomilyError::Config(e) => write!(f, "configuration error: {}", e),
+            HomilyError::Tag { path, message } => write!(f, "couldn't tag {}: {}", path.to_string_lossy(), message),
+            HomilyError::Startup(e) => write!(f, "couldn't start: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HomilyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HomilyError::Io { source, .. } => Some(source),
+            HomilyError::Http(e) => Some(e),
+            HomilyError::Parse { source, .. } => Some(source),
+            HomilyError::Json { source, .. } => Some(source),
+            HomilyError::Config(_) | HomilyError::Tag { .. } | HomilyError::Startup(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for HomilyError {
+    fn from(e: reqwest::Error) -> Self {
+        HomilyError::Http(e)
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use chrono::Duration;
+use serde::{Deserialize, Deserializer};
+use tuikit::attr::{Attr, Effect};
+
+use crate::error::Result;
+use crate::general::{bytes_pretty, Download, DownloadState, Message, Styled};
+use crate::redact::redact;
+use crate::store::{read_records, write_records, Record};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum HistoryKind {
+    Feed,
+    Episode,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Outcome {
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// How many entries `history.xml` keeps; older ones are dropped as new ones arrive.
+pub const MAX_HISTORY: usize = 1000;
+
+/// One finished feed refresh or episode download, kept in `history.xml`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    pub kind: HistoryKind,
+    pub name: String,
+    pub url: String,
+    pub path: PathBuf,
+    #[serde(deserialize_with = "parse_time")]
+    pub started: DateTime<Local>,
+    #[serde(deserialize_with = "parse_time")]
+    pub finished: DateTime<Local>,
+    #[serde(default)]
+    pub bytes: u64,
+    pub outcome: Outcome,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Records a download that has just reached `Done`, `Failed` or `Cancelled`.
+    pub fn from_download(dl: &Download) -> HistoryEntry {
+        let (kind, name) = match &dl.success_message {
+            Some(Message::FeedDownloaded(name)) => (HistoryKind::Feed, name.clone()),
+            Some(Message::EpisodeDownloaded(name)) => (HistoryKind::Episode, name.clone()),
+            _ => (HistoryKind::Episode, dl.path.file_name().unwrap_or_default().to_string_lossy().into_owned()),
+        };
+        let finished = Local::now();
+        HistoryEntry {
+            kind,
+            name,
+            url: dl.url.clone(),
+            path: dl.path.clone(),
+            started: dl.started.unwrap_or(finished),
+            finished,
+            bytes: dl.downloaded_bytes,
+            outcome: match dl.state {
+                DownloadState::Done => Outcome::Done,
+                DownloadState::Cancelled => Outcome::Cancelled,
+                _ => Outcome::Failed,
+            },
+            // Error messages can quote the request URL, tokens included.
+            error: dl.error.as_ref().map(|e| redact(&e.to_string())),
+        }
+    }
+}
+
+impl Display for HistoryEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let elapsed = (self.finished - self.started).num_seconds();
+        write!(f, "{} {:>5}s {:<9} {:>6} {:<7} {}",
+            self.started.format("%Y-%m-%d %H:%M"),
+            elapsed,
+            format!("{:?}", self.outcome).to_lowercase(),
+            bytes_pretty(self.bytes),
+            format!("{:?}", self.kind).to_lowercase(),
+            self.name)?;
+        if let Some(error) = &self.error {
+            write!(f, " - {}", redact(error))?;
+        }
+        Ok(())
+    }
+}
+
+impl Styled for HistoryEntry {
+    fn styles(&self) -> Attr {
+        if self.outcome == Outcome::Failed {
+            Attr::from(Effect::BOLD)
+        } else {
+            Attr::from(Effect::empty())
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SavedHistory {
+    #[serde(rename = "entry", default)]
+    entries: Vec<HistoryEntry>,
+}
+
+/// Loads the history, newest entry first.
+pub fn load_history(config_path: &Path) -> Result<Vec<HistoryEntry>> {
+    Ok(read_records::<SavedHistory>(&config_path.join("history.xml"))?.entries)
+}
+
+pub fn save_history(config_path: &Path, entries: &[HistoryEntry]) -> Result<()> {
+    let records: Vec<Record> = entries.iter()
+        .map(|entry| {
+            let mut record = vec![
+                ("kind", format!("{:?}", entry.kind)),
+                ("name", entry.name.clone()),
+                ("url", entry.url.clone()),
+                ("path", entry.path.to_string_lossy().into_owned()),
+                ("started", entry.started.to_rfc3339()),
+                ("finished", entry.finished.to_rfc3339()),
+                ("bytes", entry.bytes.to_string()),
+                ("outcome", format!("{:?}", entry.outcome)),
+            ];
+            if let Some(error) = &entry.error {
+                record.push(("error", error.clone()));
+            }
+            record
+        })
+        .collect();
+    write_records(&config_path.join("history.xml"), "history", "entry", &records)
+}
+
+/// Parses an age like `30m`, `12h`, `7d` or `2w`. Ages too large for a `Duration` are `None`.
+pub fn parse_age(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let (split, _) = text.char_indices().last()?;
+    let count: i64 = text[..split].parse().ok().filter(|count| *count >= 0)?;
+    match &text[split..] {
+        "s" => Duration::try_seconds(count),
+        "m" => Duration::try_minutes(count),
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        "w" => Duration::try_weeks(count),
+        _ => None,
+    }
+}
+
+fn parse_time<'de, D>(deserializer: D) -> std::result::Result<DateTime<Local>, D::Error>
+        where D: Deserializer<'de>, {
+    let s = String::deserialize(deserializer)?;
+    DateTime::parse_from_rfc3339(&s)
+        .map(|time| time.with_timezone(&Local))
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ages_without_overflowing() {
+        assert_eq!(parse_age("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_age(" 2w "), Some(Duration::weeks(2)));
+        assert_eq!(parse_age("7"), None);
+        assert_eq!(parse_age("-1d"), None);
+        assert_eq!(parse_age("1000000000000000d"), None);
+        assert!(parse_age("1000000000d").is_some_and(|age| Local::now().checked_sub_signed(age).is_none()));
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use log::info;
+use tokio::process::Command;
+
+use crate::general::{Episode, Feed};
+
+/// Environment handed to a hook describing the episode that was downloaded.
+pub fn episode_env(feed: &Feed, ep: &Episode, path: &Path) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        ("HOMILY_FEED_NAME", feed.name.clone()),
+        ("HOMILY_FEED_URL", feed.url.clone()),
+        ("HOMILY_FEED_FOLDER", feed.save_folder.clone()),
+        ("HOMILY_EPISODE_TITLE", ep.name.clone()),
+        ("HOMILY_EPISODE_URL", ep.enclosure.url.clone()),
+        ("HOMILY_FILE", path.to_string_lossy().into_owned()),
+    ];
+    if let Some(date) = ep.pub_date {
+        env.push(("HOMILY_EPISODE_DATE", date.to_rfc3339()));
+    }
+    if let Some(mime_type) = &ep.enclosure.mime_type {
+        env.push(("HOMILY_EPISODE_TYPE", mime_type.clone()));
+    }
+    env
+}
+
+/// Runs each hook with `sh -c`, one after the other, passing the file as `$1`. Output and
+/// exit status go to the log; a failing hook doesn't stop the ones after it.
+pub async fn run_hooks(hooks: Vec<String>, path: PathBuf, env: Vec<(&'static str, String)>) {
+    for hook in hooks {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&hook)
+            .arg("homily")
+            .arg(&path)
+            .envs(env.iter().cloned())
+            .stdin(Stdio::null())
+            .output()
+            .await;
+        match output {
+            Ok(output) => {
+                for line in String::from_utf8_lossy(&output.stdout).lines()
+                        .chain(String::from_utf8_lossy(&output.stderr).lines()) {
+                    info!("Hook: {}", line);
+                }
+                if output.status.success() {
+                    info!("Hook finished: {}", hook);
+                } else {
+                    info!("Hook failed ({}): {}", output.status, hook);
+                }
+            },
+            Err(e) => info!("Couldn't run hook {}: {}", hook, e),
+        }
+    }
+}
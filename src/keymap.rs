@@ -7,8 +7,33 @@ pub enum KeyMap {
     Downloads,
     Log,
     Headers,
+    History,
+    Details,
+    Transcript,
+    Chapters,
+    Play,
+    UpNext,
+    AddUpNext,
+    PlayNext,
+    ExportPlaylist,
+    TogglePlayed,
+    MarkFeedPlayed,
+    MarkOlderPlayed,
+    Search,
+    NextMatch,
+    PrevMatch,
     Download,
     Refresh,
+    Pause,
+    Resume,
+    ResumeAll,
+    Cancel,
+    Retry,
+    MoveUp,
+    MoveDown,
+    ClearFinished,
+    RateDown,
+    RateUp,
     Quit,
     Up,
     Down,
@@ -19,6 +44,8 @@ pub enum KeyMap {
     Right,
     Left,
     Enter,
+    Input(char),
+    Backspace,
     Resize(usize, usize),
 }
 
@@ -29,9 +56,34 @@ impl KeyMap {
             TKEvent::Key(TKKey::Char('f')) => Some(KeyMap::Feeds),
             TKEvent::Key(TKKey::Char('o')) => Some(KeyMap::Downloads),
             TKEvent::Key(TKKey::Char('l')) => Some(KeyMap::Log),
+            TKEvent::Key(TKKey::Char('i')) => Some(KeyMap::Details),
+            TKEvent::Key(TKKey::Char('m')) => Some(KeyMap::TogglePlayed),
+            TKEvent::Key(TKKey::Char('M')) => Some(KeyMap::MarkFeedPlayed),
+            TKEvent::Key(TKKey::Char('O')) => Some(KeyMap::MarkOlderPlayed),
+            TKEvent::Key(TKKey::Char('p')) => Some(KeyMap::Play),
+            TKEvent::Key(TKKey::Char('Q')) => Some(KeyMap::UpNext),
+            TKEvent::Key(TKKey::Char('A')) => Some(KeyMap::AddUpNext),
+            TKEvent::Key(TKKey::Char('I')) => Some(KeyMap::PlayNext),
+            TKEvent::Key(TKKey::Char('E')) => Some(KeyMap::ExportPlaylist),
+            TKEvent::Key(TKKey::Char('C')) => Some(KeyMap::Chapters),
+            TKEvent::Key(TKKey::Char('t')) => Some(KeyMap::Transcript),
+            TKEvent::Key(TKKey::Char('/')) => Some(KeyMap::Search),
+            TKEvent::Key(TKKey::Char('n')) => Some(KeyMap::NextMatch),
+            TKEvent::Key(TKKey::Char('N')) => Some(KeyMap::PrevMatch),
+            TKEvent::Key(TKKey::Char('H')) => Some(KeyMap::History),
             TKEvent::Key(TKKey::Char('h')) => Some(KeyMap::Headers),
             TKEvent::Key(TKKey::Char('d')) => Some(KeyMap::Download),
             TKEvent::Key(TKKey::Char('r')) => Some(KeyMap::Refresh),
+            TKEvent::Key(TKKey::Char('s')) => Some(KeyMap::Pause),
+            TKEvent::Key(TKKey::Char('u')) => Some(KeyMap::Resume),
+            TKEvent::Key(TKKey::Char('U')) => Some(KeyMap::ResumeAll),
+            TKEvent::Key(TKKey::Char('x')) => Some(KeyMap::Cancel),
+            TKEvent::Key(TKKey::Char('a')) => Some(KeyMap::Retry),
+            TKEvent::Key(TKKey::Char('K')) => Some(KeyMap::MoveUp),
+            TKEvent::Key(TKKey::Char('J')) => Some(KeyMap::MoveDown),
+            TKEvent::Key(TKKey::Char('c')) => Some(KeyMap::ClearFinished),
+            TKEvent::Key(TKKey::Char('[')) => Some(KeyMap::RateDown),
+            TKEvent::Key(TKKey::Char(']')) => Some(KeyMap::RateUp),
             TKEvent::Key(TKKey::Char('q')) => Some(KeyMap::Quit),
             TKEvent::Key(TKKey::Up) => Some(KeyMap::Up),
             TKEvent::Key(TKKey::Down) => Some(KeyMap::Down),
@@ -54,9 +106,34 @@ impl KeyMap {
             CTEvent::Key(CTKeyEvent { code: CTKey::Char('f'), .. }) => Some(KeyMap::Feeds),
             CTEvent::Key(CTKeyEvent { code: CTKey::Char('o'), .. }) => Some(KeyMap::Downloads),
             CTEvent::Key(CTKeyEvent { code: CTKey::Char('l'), .. }) => Some(KeyMap::Log),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('i'), .. }) => Some(KeyMap::Details),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('m'), .. }) => Some(KeyMap::TogglePlayed),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('M'), .. }) => Some(KeyMap::MarkFeedPlayed),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('O'), .. }) => Some(KeyMap::MarkOlderPlayed),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('p'), .. }) => Some(KeyMap::Play),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('Q'), .. }) => Some(KeyMap::UpNext),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('A'), .. }) => Some(KeyMap::AddUpNext),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('I'), .. }) => Some(KeyMap::PlayNext),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('E'), .. }) => Some(KeyMap::ExportPlaylist),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('C'), .. }) => Some(KeyMap::Chapters),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('t'), .. }) => Some(KeyMap::Transcript),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('/'), .. }) => Some(KeyMap::Search),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('n'), .. }) => Some(KeyMap::NextMatch),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('N'), .. }) => Some(KeyMap::PrevMatch),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('H'), .. }) => Some(KeyMap::History),
             CTEvent::Key(CTKeyEvent { code: CTKey::Char('h'), .. }) => Some(KeyMap::Headers),
             CTEvent::Key(CTKeyEvent { code: CTKey::Char('d'), .. }) => Some(KeyMap::Download),
             CTEvent::Key(CTKeyEvent { code: CTKey::Char('r'), .. }) => Some(KeyMap::Refresh),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('s'), .. }) => Some(KeyMap::Pause),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('u'), .. }) => Some(KeyMap::Resume),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('U'), .. }) => Some(KeyMap::ResumeAll),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('x'), .. }) => Some(KeyMap::Cancel),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('a'), .. }) => Some(KeyMap::Retry),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('K'), .. }) => Some(KeyMap::MoveUp),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('J'), .. }) => Some(KeyMap::MoveDown),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('c'), .. }) => Some(KeyMap::ClearFinished),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char('['), .. }) => Some(KeyMap::RateDown),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char(']'), .. }) => Some(KeyMap::RateUp),
             CTEvent::Key(CTKeyEvent { code: CTKey::Char('q'), .. }) => Some(KeyMap::Quit),
             CTEvent::Key(CTKeyEvent { code: CTKey::Up, .. }) => Some(KeyMap::Up),
             CTEvent::Key(CTKeyEvent { code: CTKey::Down, .. }) => Some(KeyMap::Down),
@@ -72,6 +149,29 @@ impl KeyMap {
             _ => None,
         }
     }
+
+    /// Keys while text is being typed, e.g. a search: letters are input rather than commands.
+    pub fn text_from_tuikit_event(event: TKEvent) -> Option<KeyMap> {
+        match event {
+            TKEvent::Key(TKKey::Char(c)) => Some(KeyMap::Input(c)),
+            TKEvent::Key(TKKey::Backspace) => Some(KeyMap::Backspace),
+            TKEvent::Key(TKKey::Enter) => Some(KeyMap::Enter),
+            TKEvent::Key(TKKey::ESC) => Some(KeyMap::Cancel),
+            TKEvent::Resize { width, height } => Some(KeyMap::Resize(width, height)),
+            _ => None,
+        }
+    }
+
+    pub fn text_from_crossterm_event(event: CTEvent) -> Option<KeyMap> {
+        match event {
+            CTEvent::Key(CTKeyEvent { code: CTKey::Char(c), .. }) => Some(KeyMap::Input(c)),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Backspace, .. }) => Some(KeyMap::Backspace),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Enter, .. }) => Some(KeyMap::Enter),
+            CTEvent::Key(CTKeyEvent { code: CTKey::Esc, .. }) => Some(KeyMap::Cancel),
+            CTEvent::Resize(width, height) => Some(KeyMap::Resize(width as usize, height as usize)),
+            _ => None,
+        }
+    }
 }
 
 /*
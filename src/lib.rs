@@ -1,22 +1,56 @@
+pub mod artwork;
+pub mod chapters;
+pub mod client;
+pub mod config;
+pub mod error;
+pub mod history;
+pub mod hooks;
 pub mod stringlogger;
 pub mod keymap;
+pub mod mpv;
+pub mod playback;
+pub mod playlist;
+pub mod player;
+pub mod ratelimit;
+pub mod redact;
+pub mod secrets;
+pub mod store;
+pub mod tagging;
+pub mod transcript;
 pub mod ui_crossterm;
+pub mod upnext;
 pub mod ui_tuikit;
 
 pub mod general {
     use std::cmp::{min, max};
+    use std::collections::{HashMap, HashSet};
     use std::default::Default;
     use std::fmt::{Display, Formatter};
+    use std::future::Future;
     use std::path::{Path, PathBuf};
     use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
     use chrono::prelude::*;
+    use crate::artwork::{finish_artwork, ArtworkJob, SavedArtwork};
+    use crate::client::{with_headers, HttpClient, RequestHeader};
+    use crate::config::Config;
+    use crate::error::HomilyError;
+    use crate::ratelimit::RateLimiter;
+    use crate::redact::{redact, redact_header};
+    use crate::secrets::Secrets;
+    use crate::transcript::{format_time, parse_time};
     use futures_channel::mpsc::UnboundedSender;
+    use futures_util::future::{abortable, AbortHandle};
     use futures_util::StreamExt;
     use log::info;
     use serde::{Deserialize, Deserializer};
     use tokio::prelude::*;
     use tokio::fs as tokio_fs;
+    use tokio::runtime::Runtime;
+    use tokio::time::{delay_for, timeout};
     use crossterm::style::{StyledContent, Stylize};
     use tuikit::attr::{Attr, Effect, Color};
     use tuikit::canvas::Canvas;
@@ -35,15 +69,17 @@ pub mod general {
                 Some((idx, _)) => &text[..idx],
             };
             if self.styles() == Attr::from(Effect::BOLD) {
-                format!("{}", text).bold().white()
+                text.to_string().bold().white()
+            } else if self.styles() == Attr::from(Effect::DIM) {
+                text.to_string().dim()
             } else {
-                format!("{}", text).stylize()
+                text.to_string().stylize()
             }
         }
 
         fn get_thing(&self) -> Thing {
             Thing {
-                text: String::from(format!("{}", &self)),
+                text: format!("{}", &self),
                 styles: self.styles(),
             }
         }
@@ -53,25 +89,56 @@ pub mod general {
     pub enum Message {
         Notification(String),
         LogMessage(String),
-        FeedUpdated,
         FeedDownloaded(String),
         EpisodeDownloaded(String),
-        DownloadProgress(String, u64),
-        DownloadSize(String, u64),
+        DownloadProgress(DownloadId, u64),
+        DownloadSize(DownloadId, u64),
+        DownloadDone(DownloadId),
+        DownloadFailed { id: DownloadId, error: DownloadError },
+        DownloadRetry(DownloadId, u32),
         Headers(Vec<Header>),
+        ArtworkSaved(SavedArtwork),
+        /// Seconds into the episode with this `Episode::key`, as reported by the player.
+        PlaybackPosition { key: String, position: f64, duration: Option<f64> },
+        PlaybackEnded(String),
     }
 
     pub trait Downloadable {
         fn url(&self) -> String;
         fn save_path(&self) -> String;
 
+        /// Size the publisher announced for the file, checked once it has been downloaded.
+        fn expected_length(&self) -> Option<u64> {
+            None
+        }
+
+        /// MIME type the publisher announced for the file.
+        fn expected_type(&self) -> Option<String> {
+            None
+        }
+
+        /// Extra headers to send when fetching the file.
+        fn request_headers(&self) -> Vec<RequestHeader> {
+            vec![]
+        }
+
+        /// The feed the file belongs to, which decides the credentials sent with the request.
+        fn feed_name(&self) -> Option<String> {
+            None
+        }
+
         fn get_download(&self, path_prefix: Option<&PathBuf>) -> Download {
             Download {
+                id: next_download_id(),
                 url: self.url(),
                 path: match path_prefix {
-                    Some(path) => (*path.join(&self.save_path())).to_path_buf(),
+                    Some(path) => path.join(self.save_path()),
                     None => PathBuf::from(&self.save_path()),
                 },
+                expected_length: self.expected_length(),
+                expected_type: self.expected_type(),
+                headers: self.request_headers(),
+                feed_name: self.feed_name(),
                 ..Default::default()
             }
         }
@@ -97,12 +164,75 @@ pub mod general {
     #[derive(Debug, Deserialize, PartialEq, Clone)]
     pub struct Enclosure {
         pub url: String,
+        #[serde(deserialize_with = "parse_length", default)]
+        pub length: Option<u64>,
+        #[serde(rename = "type", default)]
+        pub mime_type: Option<String>,
+    }
+
+    /// Either an RSS `<image><url>` or an `<itunes:image href>`.
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    pub struct Image {
+        #[serde(default)]
+        pub url: Option<String>,
+        #[serde(default)]
+        pub href: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    pub struct Guid {
+        #[serde(rename = "$value", default)]
+        pub value: String,
+    }
+
+    /// A `<podcast:transcript>` of an episode.
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    pub struct Transcript {
+        pub url: String,
+        /// Feeds sometimes leave it out; the URL then decides the format.
+        #[serde(rename = "type", default)]
+        pub mime_type: Option<String>,
+        #[serde(default)]
+        pub language: Option<String>,
+        /// `captions` when the transcript has timestamps for every cue.
+        #[serde(default)]
+        pub rel: Option<String>,
+    }
+
+    impl Transcript {
+        pub fn extension(&self) -> &'static str {
+            match self.mime_type.as_deref() {
+                Some("text/vtt") => "vtt",
+                Some("application/x-subrip") | Some("application/srt") | Some("text/srt") => "srt",
+                Some("application/json") => "json",
+                Some("text/html") => "html",
+                Some(_) => "txt",
+                None => {
+                    let path = self.url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+                    ["vtt", "srt", "json", "html"].iter().copied()
+                        .find(|ext| path.ends_with(&format!(".{}", ext)))
+                        .unwrap_or(if path.ends_with(".htm") { "html" } else { "txt" })
+                },
+            }
+        }
+    }
+
+    /// A `<podcast:chapters>` file of an episode, normally JSON chapters.
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    pub struct Chapters {
+        pub url: String,
+        #[serde(rename = "type", default)]
+        pub mime_type: Option<String>,
     }
 
     #[derive(Debug, Deserialize, PartialEq)]
     pub struct Channel {
         #[serde(rename = "item", default)]
         pub episodes: Vec<Episode>,
+        #[serde(default)]
+        pub image: Option<Image>,
+        #[serde(rename = "itunes-image", default)]
+        pub itunes_image: Option<Image>,
     }
 
     #[derive(Debug, Deserialize, PartialEq, Clone)]
@@ -114,16 +244,34 @@ pub mod general {
         #[serde(skip)]
         pub save_path: String,
         pub url: String,
+        #[serde(rename = "user-agent", default)]
+        pub user_agent: Option<String>,
+        #[serde(rename = "header", default)]
+        pub headers: Vec<RequestHeader>,
+        /// Commands run after each episode of this feed is downloaded, after the global ones.
+        #[serde(rename = "hook", default)]
+        pub hooks: Vec<String>,
+        /// Whether to tag downloaded episodes, overriding `tag-episodes` in the config.
+        #[serde(default)]
+        pub tag: Option<bool>,
         #[serde(skip)]
         pub episodes: ThingList<Episode>,
+        /// Channel artwork from the last refresh.
+        #[serde(skip)]
+        pub image_url: Option<String>,
     }
 
     impl Feed {
         pub fn check_episodes_downloaded(&mut self) {
             for ep in self.episodes.things.iter_mut() {
                 ep.downloaded = Path::new(&ep.save_path()).exists();
+                ep.failed = !ep.downloaded && invalid_path(Path::new(&ep.save_path())).exists();
             }
         }
+
+        pub fn unplayed(&self) -> usize {
+            self.episodes.things.iter().filter(|ep| !ep.played).count()
+        }
     }
 
     impl Downloadable for Feed {
@@ -134,19 +282,37 @@ pub mod general {
         fn save_path(&self) -> String {
             format!("{}.rss", &self.folder)
         }
+
+        fn request_headers(&self) -> Vec<RequestHeader> {
+            let mut headers = self.headers.clone();
+            if let Some(user_agent) = &self.user_agent {
+                headers.push(RequestHeader { name: String::from("User-Agent"), value: user_agent.clone() });
+            }
+            headers
+        }
+
+        fn feed_name(&self) -> Option<String> {
+            Some(self.name.clone())
+        }
     }
 
     impl Display for Feed {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            write!(f, "{} ({})", &self.name, &self.folder)
+            write!(f, "{} ({})", &self.name, &self.folder)?;
+            let unplayed = self.unplayed();
+            if unplayed > 0 {
+                write!(f, " [{} unplayed]", unplayed)?;
+            }
+            Ok(())
         }
     }
 
     impl Styled for Feed {
         fn styles(&self) -> Attr {
-            if self.episodes.things.len() > 0 &&
+            if !self.episodes.things.is_empty() &&
                     !self.episodes.things[0].name.to_lowercase().contains("teaser") &&
-                    !self.episodes.things[0].downloaded {
+                    !self.episodes.things[0].downloaded &&
+                    !self.episodes.things[0].played {
                 Attr::from(Effect::BOLD)
             } else {
                 Attr::from(Effect::empty())
@@ -161,8 +327,34 @@ pub mod general {
         #[serde(rename = "pubDate", deserialize_with = "parse_pub_date")]
         pub pub_date: Option<DateTime<FixedOffset>>,
         pub enclosure: Enclosure,
+        #[serde(default)]
+        pub description: Option<String>,
+        #[serde(rename = "itunes-episode", deserialize_with = "parse_number", default)]
+        pub episode_number: Option<u32>,
+        #[serde(rename = "itunes-season", deserialize_with = "parse_number", default)]
+        pub season: Option<u32>,
+        #[serde(rename = "itunes-image", default)]
+        pub itunes_image: Option<Image>,
+        #[serde(rename = "podcast-transcript", default)]
+        pub transcripts: Vec<Transcript>,
+        #[serde(rename = "podcast-chapters", default)]
+        pub chapters: Option<Chapters>,
+        #[serde(default)]
+        pub guid: Option<Guid>,
+        /// `<itunes:duration>`, as seconds or `h:mm:ss`.
+        #[serde(rename = "duration", default)]
+        pub itunes_duration: Option<String>,
         #[serde(skip)]
         pub downloaded: bool,
+        /// Where playback stopped last time.
+        #[serde(skip)]
+        pub position: Option<Duration>,
+        #[serde(skip)]
+        pub duration: Option<Duration>,
+        #[serde(skip)]
+        pub played: bool,
+        #[serde(skip)]
+        pub failed: bool,
         #[serde(skip)]
         pub feed: Option<Rc<Feed>>,
     }
@@ -171,6 +363,95 @@ pub mod general {
         pub fn filename(&self) -> String {
             format!("{}.mp3", &self.name.replace("/", "_"))
         }
+
+        /// Identifies the episode across refreshes: its GUID, or the enclosure URL without one.
+        pub fn key(&self) -> String {
+            self.guid.as_ref()
+                .map(|guid| guid.value.trim().to_string())
+                .filter(|guid| !guid.is_empty())
+                .unwrap_or_else(|| self.enclosure.url.clone())
+        }
+
+        /// Where playback should start: the last position, unless the episode was finished.
+        pub fn resume_position(&self) -> Duration {
+            match self.position {
+                Some(position) if !self.played => position,
+                _ => Duration::from_secs(0),
+            }
+        }
+
+        /// Length reported by the player, or else the one the feed announces.
+        pub fn length(&self) -> Option<Duration> {
+            self.duration.or_else(|| self.itunes_duration.as_deref().and_then(parse_time))
+        }
+
+        /// Where a transcript is saved: next to the audio file, with the transcript's extension.
+        pub fn transcript_path(&self, transcript: &Transcript) -> PathBuf {
+            Path::new(&self.save_path()).with_extension(transcript.extension())
+        }
+
+        pub fn chapters_path(&self) -> PathBuf {
+            Path::new(&self.save_path()).with_extension("chapters.json")
+        }
+
+        /// Downloads for the transcripts and chapters that go with the audio file. Only the
+        /// first transcript of each format is fetched, since they would share a file name.
+        pub fn extra_downloads(&self, transcripts: bool, chapters: bool) -> Vec<Download> {
+            let extra = |url: &str, path: PathBuf, expected_type: Option<String>| Download {
+                id: next_download_id(),
+                url: url.to_string(),
+                path,
+                expected_type,
+                headers: self.request_headers(),
+                feed_name: self.feed_name(),
+                ..Default::default()
+            };
+            let mut downloads: Vec<Download> = vec![];
+            if transcripts {
+                for transcript in &self.transcripts {
+                    let path = self.transcript_path(transcript);
+                    if !downloads.iter().any(|dl| dl.path == path) {
+                        downloads.push(extra(&transcript.url, path, transcript.mime_type.clone()));
+                    }
+                }
+            }
+            if let Some(chapters_file) = self.chapters.as_ref().filter(|_| chapters) {
+                downloads.push(extra(&chapters_file.url, self.chapters_path(), chapters_file.mime_type.clone()));
+            }
+            downloads
+        }
+
+        /// Lines for the details view.
+        pub fn details(&self) -> Vec<String> {
+            let on_disk = |path: &Path| if path.exists() { " (downloaded)" } else { "" };
+            let mut lines = vec![
+                format!("Title:       {}", self.name),
+                format!("Feed:        {}", self.feed_name().unwrap_or_default()),
+                format!("Published:   {}", self.pub_date.map_or(String::from("unknown"), |dt| dt.to_string())),
+                format!("Audio:       {}{}", redact(&self.enclosure.url), on_disk(Path::new(&self.save_path()))),
+                format!("Size:        {}", self.enclosure.length.map_or(String::from("unknown"), bytes_pretty)),
+                format!("Type:        {}", self.enclosure.mime_type.as_deref().unwrap_or("unknown")),
+            ];
+            if self.episode_number.is_some() || self.season.is_some() {
+                lines.push(format!("Episode:     {}{}",
+                    self.episode_number.map_or(String::new(), |n| n.to_string()),
+                    self.season.map_or(String::new(), |n| format!(" (season {})", n))));
+            }
+            if self.transcripts.is_empty() {
+                lines.push(String::from("Transcripts: none"));
+            }
+            for transcript in &self.transcripts {
+                lines.push(format!("Transcript:  {}{}{}",
+                    transcript.mime_type.as_deref().unwrap_or(transcript.extension()),
+                    transcript.language.as_ref().map_or(String::new(), |language| format!(", {}", language)),
+                    on_disk(&self.transcript_path(transcript))));
+            }
+            lines.push(match &self.chapters {
+                Some(_) => format!("Chapters:    yes{}", on_disk(&self.chapters_path())),
+                None => String::from("Chapters:    none"),
+            });
+            lines
+        }
     }
 
     impl Downloadable for Episode {
@@ -181,6 +462,22 @@ pub mod general {
         fn save_path(&self) -> String {
             format!("{}/{}", &self.feed.as_ref().unwrap().save_folder, &self.filename())
         }
+
+        fn expected_length(&self) -> Option<u64> {
+            self.enclosure.length
+        }
+
+        fn expected_type(&self) -> Option<String> {
+            self.enclosure.mime_type.clone()
+        }
+
+        fn request_headers(&self) -> Vec<RequestHeader> {
+            self.feed.as_ref().map_or(vec![], |feed| feed.request_headers())
+        }
+
+        fn feed_name(&self) -> Option<String> {
+            self.feed.as_ref().map(|feed| feed.name.clone())
+        }
     }
 
     impl Display for Episode {
@@ -189,13 +486,28 @@ pub mod general {
                 &self.name, 
                 &self.pub_date.map_or(String::from("date unknown"), |dt| dt.to_string()),
                 //&self.downloaded,
-            )
+            )?;
+            if self.failed {
+                write!(f, " [failed]")?;
+            }
+            if self.played {
+                write!(f, " [played]")?;
+            }
+            if let Some(position) = self.position.filter(|position| !self.played && position.as_secs() > 0) {
+                match self.duration {
+                    Some(duration) => write!(f, " {} / {}", format_time(position), format_time(duration))?,
+                    None => write!(f, " {}", format_time(position))?,
+                }
+            }
+            Ok(())
         }
     }
 
     impl Styled for Episode {
         fn styles(&self) -> Attr {
-            if self.downloaded {
+            if self.played {
+                Attr::from(Effect::DIM)
+            } else if self.downloaded {
                 Attr::from(Effect::BOLD)
             } else {
                 Attr::from(Effect::empty())
@@ -219,22 +531,304 @@ pub mod general {
 
     impl Styled for Header {}
 
+    #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+    pub enum DownloadState {
+        #[default]
+        Queued,
+        Active,
+        Paused,
+        Done,
+        Failed,
+        Cancelled,
+    }
+
+    impl Display for DownloadState {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", match self {
+                DownloadState::Queued => "queued",
+                DownloadState::Active => "active",
+                DownloadState::Paused => "paused",
+                DownloadState::Done => "done",
+                DownloadState::Failed => "failed",
+                DownloadState::Cancelled => "cancelled",
+            })
+        }
+    }
+
+    /// Why a download failed, sorted into the cases a user can act on.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum DownloadError {
+        Dns(String),
+        Tls(String),
+        Connection(String),
+        Timeout,
+        HttpStatus(u16),
+        DiskFull,
+        Permission(String),
+        Io(String),
+        Verification(String),
+        Other(String),
+    }
+
+    impl DownloadError {
+        pub fn from_reqwest(e: &reqwest::Error) -> DownloadError {
+            // reqwest doesn't expose resolver or TLS failures as types, only through the
+            // messages of the errors it wraps.
+            let mut chain = e.to_string();
+            let mut source = std::error::Error::source(e);
+            while let Some(inner) = source {
+                chain.push_str(": ");
+                chain.push_str(&inner.to_string());
+                source = inner.source();
+            }
+            let lowercase = chain.to_lowercase();
+            if e.is_timeout() {
+                DownloadError::Timeout
+            } else if lowercase.contains("dns error") || lowercase.contains("failed to lookup address") {
+                DownloadError::Dns(chain)
+            } else if lowercase.contains("certificate verify failed") || lowercase.contains("self signed certificate")
+                    || lowercase.contains("unable to get local issuer certificate") {
+                let host = e.url().and_then(|url| url.host_str()).unwrap_or("the server").to_string();
+                DownloadError::Tls(format!(
+                    "certificate of {} isn't trusted, add its CA with <ca-certificate> in config.xml ({})", host, chain))
+            } else if lowercase.contains("certificate") || lowercase.contains("ssl") || lowercase.contains("tls") {
+                DownloadError::Tls(chain)
+            } else if e.is_connect() || e.is_request() || e.is_body() || e.is_decode() {
+                DownloadError::Connection(chain)
+            } else {
+                DownloadError::Other(chain)
+            }
+        }
+
+        pub fn from_io(e: &std::io::Error) -> DownloadError {
+            match e.kind() {
+                std::io::ErrorKind::StorageFull => DownloadError::DiskFull,
+                std::io::ErrorKind::PermissionDenied => DownloadError::Permission(e.to_string()),
+                _ => DownloadError::Io(e.to_string()),
+            }
+        }
+
+        /// Network hiccups, timeouts and 5xx or 429 responses may go away on their own.
+        pub fn is_transient(&self) -> bool {
+            match self {
+                DownloadError::Connection(_) | DownloadError::Timeout => true,
+                DownloadError::HttpStatus(status) => *status == 429 || *status >= 500,
+                _ => false,
+            }
+        }
+    }
+
+    impl Display for DownloadError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                DownloadError::Dns(e) => write!(f, "DNS lookup failed: {}", e),
+                DownloadError::Tls(e) => write!(f, "TLS error: {}", e),
+                DownloadError::Connection(e) => write!(f, "connection error: {}", e),
+                DownloadError::Timeout => write!(f, "timed out"),
+                DownloadError::HttpStatus(status) => write!(f, "HTTP {}", status),
+                DownloadError::DiskFull => write!(f, "disk full"),
+                DownloadError::Permission(e) => write!(f, "permission denied: {}", e),
+                DownloadError::Io(e) => write!(f, "file error: {}", e),
+                DownloadError::Verification(e) => write!(f, "bad download: {}", e),
+                DownloadError::Other(e) => write!(f, "{}", e),
+            }
+        }
+    }
+
+    /// Identifies one entry in the downloads list. The same URL downloaded twice gets two ids.
+    pub type DownloadId = u64;
+
+    static NEXT_DOWNLOAD_ID: AtomicU64 = AtomicU64::new(1);
+
+    pub fn next_download_id() -> DownloadId {
+        NEXT_DOWNLOAD_ID.fetch_add(1, AtomicOrdering::Relaxed)
+    }
+
     #[derive(Clone, Default)]
     pub struct Download {
+        pub id: DownloadId,
         pub url: String,
         pub path: PathBuf,
         pub downloaded_bytes: u64,
         pub total_bytes: u64,
         pub success_message: Option<Message>,
+        pub expected_length: Option<u64>,
+        pub expected_type: Option<String>,
+        pub headers: Vec<RequestHeader>,
+        pub feed_name: Option<String>,
+        pub state: DownloadState,
+        pub rate_limit: Option<u64>,
+        pub max_retries: u32,
+        pub retries: u32,
+        pub error: Option<DownloadError>,
+        pub started: Option<DateTime<Local>>,
+        pub abort_handle: Option<AbortHandle>,
+        /// Set for pictures, which are named after their type once fetched.
+        pub artwork: Option<ArtworkJob>,
+    }
+
+    impl Download {
+        pub fn host(&self) -> String {
+            reqwest::Url::parse(&self.url).ok()
+                .and_then(|url| url.host_str().map(String::from))
+                .unwrap_or_default()
+        }
+
+        fn abort(&mut self) {
+            if let Some(handle) = self.abort_handle.take() {
+                handle.abort();
+            }
+        }
     }
 
     impl Display for Download {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            write!(f, "{} {}", bytes_pretty(self.downloaded_bytes), &self.path.file_name().unwrap().to_string_lossy())
+            let bytes = if self.total_bytes > 0 {
+                format!("{}/{}", bytes_pretty(self.downloaded_bytes), bytes_pretty(self.total_bytes))
+            } else {
+                bytes_pretty(self.downloaded_bytes)
+            };
+            write!(f, "{:<9} {:>13} {}", self.state, bytes, &self.path.file_name().unwrap_or_default().to_string_lossy())?;
+            if self.retries > 0 {
+                write!(f, " (retry {})", self.retries)?;
+            }
+            if let Some(error) = &self.error {
+                write!(f, " - {}", redact(&error.to_string()))?;
+            }
+            Ok(())
         }
     }
 
-    impl Styled for Download {}
+    impl Styled for Download {
+        fn styles(&self) -> Attr {
+            if self.state == DownloadState::Active {
+                Attr::from(Effect::BOLD)
+            } else {
+                Attr::from(Effect::empty())
+            }
+        }
+    }
+
+    impl ThingList<Download> {
+        pub fn find(&mut self, id: DownloadId) -> Option<&mut Download> {
+            self.things.iter_mut().find(|dl| dl.id == id)
+        }
+
+        /// Whether a download of `path` is waiting, running or paused.
+        pub fn has_pending(&self, path: &Path) -> bool {
+            self.things.iter().any(|dl| dl.path == path && matches!(dl.state,
+                DownloadState::Queued | DownloadState::Active | DownloadState::Paused))
+        }
+
+        /// Adds `dl` unless its file is already pending, as two downloads of one path would
+        /// share its partial file. Returns whether it was added.
+        pub fn enqueue(&mut self, dl: Download) -> bool {
+            if self.has_pending(&dl.path) {
+                return false;
+            }
+            self.things.push(dl);
+            true
+        }
+
+        /// Starts queued downloads in list order, keeping within the global and per-host
+        /// limits from `config`. Queued items whose host is busy are skipped, not blocking,
+        /// and so are those whose file another download is still writing.
+        pub fn start_queued(&mut self, runtime: &Runtime, context: &DownloadContext, config: &Config) {
+            let mut active_hosts: HashMap<String, usize> = HashMap::new();
+            let mut active_paths: HashSet<PathBuf> = HashSet::new();
+            for dl in self.things.iter().filter(|dl| dl.state == DownloadState::Active) {
+                *active_hosts.entry(dl.host()).or_insert(0) += 1;
+                active_paths.insert(dl.path.clone());
+            }
+            let mut active: usize = active_hosts.values().sum();
+            for dl in self.things.iter_mut().filter(|dl| dl.state == DownloadState::Queued) {
+                if active >= config.max_downloads {
+                    break;
+                }
+                let host_count = active_hosts.entry(dl.host()).or_insert(0);
+                if *host_count >= config.max_downloads_per_host || active_paths.contains(&dl.path) {
+                    continue;
+                }
+                dl.rate_limit = config.download_rate_limit;
+                dl.max_retries = config.max_retries;
+                let (task, handle) = abortable(download(context.clone(), dl.clone()));
+                dl.state = DownloadState::Active;
+                dl.started.get_or_insert_with(Local::now);
+                dl.abort_handle = Some(handle);
+                runtime.spawn(task);
+                active_paths.insert(dl.path.clone());
+                *host_count += 1;
+                active += 1;
+            }
+        }
+
+        /// Stops the selected download, keeping the partial file so it can be resumed.
+        pub fn pause_current(&mut self) {
+            if self.things.is_empty() {
+                return;
+            }
+            let dl = self.current();
+            if dl.state == DownloadState::Queued || dl.state == DownloadState::Active {
+                dl.abort();
+                dl.state = DownloadState::Paused;
+            }
+        }
+
+        pub fn resume_current(&mut self) {
+            if !self.things.is_empty() && self.current().state == DownloadState::Paused {
+                self.current().state = DownloadState::Queued;
+            }
+        }
+
+        pub fn resume_all(&mut self) {
+            for dl in self.things.iter_mut().filter(|dl| dl.state == DownloadState::Paused) {
+                dl.state = DownloadState::Queued;
+            }
+        }
+
+        /// Aborts the selected download and removes the partial file. Whatever was at the
+        /// destination before, such as the last copy of a feed, stays.
+        pub fn cancel_current(&mut self) {
+            if self.things.is_empty() {
+                return;
+            }
+            let dl = self.current();
+            match dl.state {
+                DownloadState::Active | DownloadState::Paused => {
+                    dl.abort();
+                    let part = part_path(&dl.path);
+                    if let Err(e) = std::fs::remove_file(&part) {
+                        if e.kind() != std::io::ErrorKind::NotFound {
+                            info!("Couldn't remove {}: {}", &part.to_string_lossy(), e);
+                        }
+                    }
+                    dl.state = DownloadState::Cancelled;
+                },
+                DownloadState::Queued => dl.state = DownloadState::Cancelled,
+                _ => {},
+            }
+        }
+
+        pub fn retry_current(&mut self) {
+            if self.things.is_empty() {
+                return;
+            }
+            let dl = self.current();
+            if dl.state == DownloadState::Failed || dl.state == DownloadState::Cancelled {
+                dl.downloaded_bytes = 0;
+                dl.total_bytes = 0;
+                dl.retries = 0;
+                dl.error = None;
+                dl.state = DownloadState::Queued;
+            }
+        }
+
+        /// Removes done and cancelled downloads; failed ones stay so they can be retried.
+        pub fn clear_finished(&mut self) {
+            self.things.retain(|dl| dl.state != DownloadState::Done && dl.state != DownloadState::Cancelled);
+            self.selected_index = min(self.selected_index, self.things.len().saturating_sub(1));
+        }
+    }
 
     #[derive(Clone, Debug)]
     pub struct ThingList<T> {
@@ -251,6 +845,16 @@ pub mod general {
             self.selected_index = (self.selected_index as i32 + offset)
                 .rem_euclid(self.things.len() as i32) as usize
         }
+
+        /// Swaps the selected item with the one `offset` away and keeps it selected, e.g. to
+        /// change the order downloads start or Up Next plays in.
+        pub fn move_current(&mut self, offset: i32) {
+            let target = self.selected_index as i32 + offset;
+            if target >= 0 && (target as usize) < self.things.len() {
+                self.things.swap(self.selected_index, target as usize);
+                self.selected_index = target as usize;
+            }
+        }
     }
 
     impl<A, B> PartialEq<ThingList<B>> for ThingList<A> where A: PartialEq<B> {
@@ -267,11 +871,11 @@ pub mod general {
 
     impl<T> Draw for ThingList<T> where T: Display + Styled {
         fn draw(&self, canvas: &mut dyn Canvas) -> DrawResult<()> {
-            if self.things.len() > 0 {
+            if !self.things.is_empty() {
                 let (_, height) = canvas.size()?;
                 let end = max(self.selected_index + 1, height);
                 let end = min(end, self.things.len());
-                let start = if end > height { end - height } else { 0 };
+                let start = end.saturating_sub(height);
                 //info!("start: {}, end: {}, height: {}", start, end, height);
                 for (i, item) in self.things[start..end].iter().enumerate() {
                     let _ = canvas.print_with_attr(i, 0, 
@@ -304,10 +908,9 @@ pub mod general {
     }
 
     const DEFAULT: Attr = Attr{ fg: Color::WHITE, bg: Color::BLACK, effect: Effect::empty() };
-    const LIGHT_BLUE: Attr = Attr{ fg: Color::LIGHT_BLUE, bg: Color::BLACK, effect: Effect::empty() };
     const BLUE: Attr = Attr{ fg: Color::BLUE, bg: Color::BLACK, effect: Effect::empty() };
 
-    pub fn get_things<T>(items: &Vec<T>) -> Vec<Thing>
+    pub fn get_things<T>(items: &[T]) -> Vec<Thing>
             where T: Styled {
         items.iter().map(|i| i.get_thing()).collect()
     }
@@ -321,6 +924,40 @@ pub mod general {
         }
     }
 
+    /// Treats missing, unparseable and zero lengths alike, since feeds use all three for "unknown".
+    pub fn parse_length<'de, D>(deserializer: D,) -> Result<Option<u64>, D::Error>
+            where D: Deserializer<'de>, {
+        if let Ok(s) = String::deserialize(deserializer) {
+            Ok(s.trim().parse::<u64>().ok().filter(|&length| length > 0))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads episode and season numbers, ignoring empty or non-numeric ones like `Bonus`.
+    pub fn parse_number<'de, D>(deserializer: D,) -> Result<Option<u32>, D::Error>
+            where D: Deserializer<'de>, {
+        if let Ok(s) = String::deserialize(deserializer) {
+            Ok(s.trim().parse::<u32>().ok())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Where a download is written until it has been verified and moved to `path`.
+    pub fn part_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".part");
+        PathBuf::from(name)
+    }
+
+    /// Where a download that failed verification is moved, so it isn't taken for a good file.
+    pub fn invalid_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".invalid");
+        PathBuf::from(name)
+    }
+
     pub fn bytes_pretty(byte_count: u64) -> String {
         match byte_count {
             bytes if bytes >= (1000 * 1000) => format!("{}.{}M", bytes / (1000 * 1000), bytes.rem_euclid(1000 * 1000) / 100_000),
@@ -329,57 +966,332 @@ pub mod general {
         }
     }
 
-    pub async fn head(utx: UnboundedSender<Message>, url: String) {
-        let client = reqwest::Client::new();
-        match client.head(&url).send().await {
-            Ok(resp) => {
-                let headers = resp.headers().iter().map(
-                    |(key, value)| Header { 
-                        name: String::from(key.as_str()),
-                        url: String::from(value.to_str().unwrap()),
-                    }
-                ).collect();
+    pub async fn fetch_headers(context: &DownloadContext, dl: &Download) -> Result<Vec<Header>, HomilyError> {
+        let request = context.secrets.request(context.client.for_url(&dl.url), reqwest::Method::HEAD, &dl.url, dl.feed_name.as_deref());
+        let resp = with_headers(request, &dl.headers).send().await?;
+        Ok(resp.headers().iter().map(
+            |(key, value)| Header { 
+                name: String::from(key.as_str()),
+                url: redact_header(key.as_str(), &String::from_utf8_lossy(value.as_bytes())),
+            }
+        ).collect())
+    }
+
+    pub async fn head(context: DownloadContext, dl: Download) {
+        let utx = &context.utx;
+        match fetch_headers(&context, &dl).await {
+            Ok(headers) => {
                 utx.unbounded_send(Message::Headers(headers))
                     .unwrap_or_else(|e| info!("err chunk {}", e));
             },
-            Err(_) => info!("Couldn't get headers"),
+            Err(HomilyError::Http(e)) => {
+                let error = DownloadError::from_reqwest(&e);
+                info!("Couldn't get headers: {}", error);
+                utx.unbounded_send(Message::Notification(format!("Couldn't get headers: {}", error)))
+                    .unwrap_or_else(|e| info!("err chunk {}", e));
+            },
+            Err(e) => info!("Couldn't get headers: {}", e),
         }
     }
 
-    pub async fn download(utx: UnboundedSender<Message>, dl: Download) {
+    /// What all download tasks share.
+    #[derive(Clone)]
+    pub struct DownloadContext {
+        pub utx: UnboundedSender<Message>,
+        pub client: HttpClient,
+        pub limiter: Arc<RateLimiter>,
+        pub secrets: Arc<Secrets>,
+        /// Longest wait for the next chunk of a response, if any.
+        pub read_timeout: Option<Duration>,
+    }
+
+    pub async fn download(context: DownloadContext, dl: Download) {
+        let utx = &context.utx;
         info!("downloading {}", &dl.url);
-        if let Ok(r) = reqwest::get(&dl.url).await {
-            let mut stream = r.bytes_stream();
-            info!("url: {}", &dl.url);
-            info!("file ok:{}", &dl.path.to_string_lossy());
-            let file = tokio_fs::File::create(&dl.path).await;
-            let mut total_bytes = 0_u64;
-            let mut read_bytes = 0_u64;
-            match file {
-                Ok(mut f) => {
-                    while let Some(y) = stream.next().await {
-                        let bytes = y.unwrap();
-                        read_bytes += bytes.len() as u64;
-                        match f.write_all(&bytes).await {
-                            Ok(_) => {
-                                total_bytes += bytes.len() as u64;
-                                utx.unbounded_send(Message::Notification(format!("bytes:{:>8}{:>8}", bytes_pretty(read_bytes), bytes_pretty(total_bytes))))
-                                    .unwrap_or_else(|e| info!("err chunk {}", e));
-                                utx.unbounded_send(Message::DownloadProgress(dl.url.clone(), read_bytes))
-                                    .unwrap_or_else(|e| info!("err chunk {}", e));
-                            },
-                            Err(e) => info!("Error writing to {}: {}", &dl.path.to_string_lossy(), e),
+        let part = part_path(&dl.path);
+        let mut resume = dl.downloaded_bytes > 0;
+        // A fresh start must not resume from a partial file some earlier download left behind.
+        if !resume {
+            let _ = tokio_fs::remove_file(&part).await;
+        }
+        let mut retries = 0;
+        let result = loop {
+            match transfer(&context, &dl, &part, resume).await {
+                Ok(response) if response.not_modified => {
+                    let _ = tokio_fs::remove_file(&part).await;
+                    break Ok(());
+                },
+                Ok(response) => match verify(&dl, &part, &response).await {
+                    Ok(()) => {
+                        let _ = tokio_fs::remove_file(invalid_path(&dl.path)).await;
+                        break match &dl.artwork {
+                            Some(job) => finish_artwork(job, &part, response.content_type.as_deref(), response.etag).await
+                                .map(|saved| utx.unbounded_send(Message::ArtworkSaved(saved))
+                                    .unwrap_or_else(|e| info!("err chunk {}", e))),
+                            None => tokio_fs::rename(&part, &dl.path).await.map_err(|e| DownloadError::from_io(&e)),
+                        };
+                    },
+                    Err(error) => {
+                        if let Err(e) = tokio_fs::rename(&part, invalid_path(&dl.path)).await {
+                            info!("Couldn't move {} aside: {}", &part.to_string_lossy(), e);
                         }
-                    }
-                    if let Some(msg) = dl.success_message {
-                        utx.unbounded_send(msg)
-                            .unwrap_or_else(|e| info!("err chunk {}", e));
-                    }
+                        break Err(error);
+                    },
+                },
+                Err(failure) if failure.error.is_transient() && retries < dl.max_retries => {
+                    retries += 1;
+                    let wait = failure.retry_after.unwrap_or_else(|| backoff(retries));
+                    info!("Retrying {} in {}s ({}/{}): {}", &dl.url, wait.as_secs(), retries, dl.max_retries, failure.error);
+                    utx.unbounded_send(Message::DownloadRetry(dl.id, retries))
+                        .unwrap_or_else(|e| info!("err chunk {}", e));
+                    delay_for(wait).await;
+                    resume = true;
                 },
-                Err(e) => info!("file error: {}", e),
+                Err(failure) => break Err(failure.error),
             }
+        };
+        match result {
+            Ok(()) => {
+                utx.unbounded_send(Message::DownloadDone(dl.id))
+                    .unwrap_or_else(|e| info!("err chunk {}", e));
+                if let Some(msg) = dl.success_message {
+                    utx.unbounded_send(msg)
+                        .unwrap_or_else(|e| info!("err chunk {}", e));
+                }
+            },
+            Err(error) => {
+                info!("Download failed {}: {}", &dl.url, error);
+                utx.unbounded_send(Message::DownloadFailed { id: dl.id, error })
+                    .unwrap_or_else(|e| info!("err chunk {}", e));
+            },
+        }
+    }
+
+    /// What the server said about the body it sent.
+    struct ResponseInfo {
+        content_length: Option<u64>,
+        content_type: Option<String>,
+        etag: Option<String>,
+        /// The server answered `If-None-Match` with 304: the saved copy is current.
+        not_modified: bool,
+    }
+
+    /// Checks a finished download against the size and type the server and the feed announced.
+    /// The enclosure length is only used when the server sent no `Content-Length`, as feeds
+    /// are often stale about sizes.
+    async fn verify(dl: &Download, path: &Path, response: &ResponseInfo) -> Result<(), DownloadError> {
+        let size = tokio_fs::metadata(path).await.map(|m| m.len()).map_err(|e| DownloadError::from_io(&e))?;
+        if size == 0 {
+            return Err(DownloadError::Verification(String::from("empty file")));
+        }
+        match (response.content_length, dl.expected_length) {
+            (Some(expected), _) | (None, Some(expected)) if expected != size =>
+                return Err(DownloadError::Verification(format!("size {} instead of {}", size, expected))),
+            _ => {},
+        }
+        // Login and error pages come back as HTML whatever the feed announced, so only an
+        // enclosure that is meant to be HTML skips this.
+        if dl.expected_type.as_ref().is_some_and(|t| t.contains("html")) {
+            return Ok(());
+        }
+        let mut start = [0_u8; 256];
+        let read = tokio_fs::File::open(path).await.map_err(|e| DownloadError::from_io(&e))?
+            .read(&mut start).await.map_err(|e| DownloadError::from_io(&e))?;
+        let start = String::from_utf8_lossy(&start[..read]).trim_start_matches(|c: char| c.is_whitespace() || c == '\u{feff}')
+            .to_lowercase();
+        // Servers often label RSS as text/html, so feeds are judged by their body alone.
+        let feed = matches!(dl.success_message, Some(Message::FeedDownloaded(_)));
+        let html_type = !feed && response.content_type.as_ref().is_some_and(|t| t.to_lowercase().starts_with("text/html"));
+        if html_type || start.starts_with("<!doctype html") || start.starts_with("<html") {
+            let wanted = dl.expected_type.as_deref().unwrap_or("the file");
+            return Err(DownloadError::Verification(format!("HTML page instead of {}", wanted)));
+        }
+        Ok(())
+    }
+
+    struct TransferFailure {
+        error: DownloadError,
+        /// The delay the server asked for before trying again, if it sent one.
+        retry_after: Option<Duration>,
+    }
+
+    impl From<DownloadError> for TransferFailure {
+        fn from(error: DownloadError) -> Self {
+            TransferFailure { error, retry_after: None }
+        }
+    }
+
+    async fn with_read_timeout<F: Future>(limit: Option<Duration>, future: F) -> Result<F::Output, DownloadError> {
+        match limit {
+            Some(limit) => timeout(limit, future).await.map_err(|_| DownloadError::Timeout),
+            None => Ok(future.await),
+        }
+    }
+
+    /// Longest wait between retries in seconds, whether chosen by backoff or by the server.
+    const MAX_RETRY_DELAY: u64 = 300;
+
+    /// Exponential backoff starting at one second and capped at five minutes, with up to
+    /// half of the delay taken off at random so that parallel retries spread out.
+    fn backoff(attempt: u32) -> Duration {
+        let delay = min(1_u64 << min(attempt - 1, 16), MAX_RETRY_DELAY) as f64;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.subsec_nanos()).unwrap_or(0);
+        let jitter = (nanos % 1000) as f64 / 1000.0;
+        Duration::from_secs_f64(delay * (1.0 - jitter / 2.0))
+    }
+
+    /// Reads a `Retry-After` header given either in seconds or as an HTTP date. A server
+    /// asking for more than `MAX_RETRY_DELAY` gets that, so a download never stalls for hours.
+    fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+        let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+        let delay = match value.parse::<u64>() {
+            Ok(seconds) => Duration::from_secs(seconds),
+            Err(_) => {
+                let date = DateTime::parse_from_rfc2822(value).ok()?;
+                (date.with_timezone(&Utc) - Utc::now()).to_std().ok()?
+            },
+        };
+        Some(min(delay, Duration::from_secs(MAX_RETRY_DELAY)))
+    }
+
+    /// Streams `dl.url` into `part`. With `resume` set, the partial file is continued with a
+    /// range request. Each chunk is held back as long as the global limiter or the
+    /// download's own `rate_limit` requires.
+    async fn transfer(context: &DownloadContext, dl: &Download, part: &Path, resume: bool)
+            -> Result<ResponseInfo, TransferFailure> {
+        let utx = &context.utx;
+        let own_limiter = RateLimiter::new(dl.rate_limit, vec![]);
+        let offset = if resume {
+            tokio_fs::metadata(part).await.map(|m| m.len()).unwrap_or(0)
+        } else {
+            0
+        };
+        let request = context.secrets.request(context.client.for_url(&dl.url), reqwest::Method::GET, &dl.url, dl.feed_name.as_deref());
+        let mut request = with_headers(request, &dl.headers);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let r = with_read_timeout(context.read_timeout, request.send()).await?
+            .map_err(|e| DownloadError::from_reqwest(&e))?;
+        let status = r.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(ResponseInfo { content_length: None, content_type: None, etag: None, not_modified: true });
+        }
+        if !status.is_success() {
+            return Err(TransferFailure {
+                error: DownloadError::HttpStatus(status.as_u16()),
+                retry_after: retry_after(r.headers()),
+            });
+        }
+        let resuming = offset > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+        let content_length = r.content_length().map(|length| if resuming { offset + length } else { length });
+        if let Some(total) = content_length {
+            utx.unbounded_send(Message::DownloadSize(dl.id, total))
+                .unwrap_or_else(|e| info!("err chunk {}", e));
+        }
+        let header = |name| r.headers().get(name)
+            .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+            .map(String::from);
+        let content_type = header(reqwest::header::CONTENT_TYPE);
+        let etag = header(reqwest::header::ETAG);
+        let mut stream = r.bytes_stream();
+        info!("url: {}", &dl.url);
+        info!("file ok:{}", &part.to_string_lossy());
+        let mut f = if resuming {
+            tokio_fs::OpenOptions::new().append(true).open(part).await
         } else {
-            info!("Error downloading: {}", &dl.url);
+            tokio_fs::File::create(part).await
+        }.map_err(|e| DownloadError::from_io(&e))?;
+        let mut total_bytes = 0_u64;
+        let mut read_bytes = if resuming { offset } else { 0 };
+        while let Some(y) = with_read_timeout(context.read_timeout, stream.next()).await? {
+            let bytes = y.map_err(|e| DownloadError::from_reqwest(&e))?;
+            read_bytes += bytes.len() as u64;
+            let wait = max(context.limiter.reserve(bytes.len() as u64), own_limiter.reserve(bytes.len() as u64));
+            if wait > Duration::from_millis(0) {
+                delay_for(wait).await;
+            }
+            f.write_all(&bytes).await.map_err(|e| DownloadError::from_io(&e))?;
+            total_bytes += bytes.len() as u64;
+            utx.unbounded_send(Message::Notification(format!("bytes:{:>8}{:>8}", bytes_pretty(read_bytes), bytes_pretty(total_bytes))))
+                .unwrap_or_else(|e| info!("err chunk {}", e));
+            utx.unbounded_send(Message::DownloadProgress(dl.id, read_bytes))
+                .unwrap_or_else(|e| info!("err chunk {}", e));
+        }
+        Ok(ResponseInfo { content_length, content_type, etag, not_modified: false })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn retry_after_is_capped() {
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(reqwest::header::RETRY_AFTER, "120".parse().unwrap());
+            assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+            headers.insert(reqwest::header::RETRY_AFTER, "86400".parse().unwrap());
+            assert_eq!(retry_after(&headers), Some(Duration::from_secs(MAX_RETRY_DELAY)));
+            headers.insert(reqwest::header::RETRY_AFTER, "Fri, 01 Jan 2100 00:00:00 GMT".parse().unwrap());
+            assert_eq!(retry_after(&headers), Some(Duration::from_secs(MAX_RETRY_DELAY)));
+            headers.insert(reqwest::header::RETRY_AFTER, "soon".parse().unwrap());
+            assert_eq!(retry_after(&headers), None);
+        }
+
+        #[tokio::test]
+        async fn html_is_rejected_unless_announced() {
+            let path = std::env::temp_dir().join(format!("homily-verify-{}.part", std::process::id()));
+            std::fs::write(&path, "\n<!DOCTYPE html><html>Please log in</html>").unwrap();
+            let response = |content_type: &str| ResponseInfo {
+                content_length: None,
+                content_type: Some(content_type.to_string()),
+                etag: None,
+                not_modified: false,
+            };
+            let untyped = Download::default();
+            let page = Download { expected_type: Some(String::from("text/html")), ..Default::default() };
+            assert!(verify(&untyped, &path, &response("application/octet-stream")).await.is_err());
+            assert!(verify(&page, &path, &response("text/html")).await.is_ok());
+            std::fs::write(&path, "ID3 audio").unwrap();
+            assert!(verify(&untyped, &path, &response("audio/mpeg")).await.is_ok());
+            assert!(verify(&untyped, &path, &response("Text/HTML; charset=utf-8")).await.is_err());
+            let feed = Download { success_message: Some(Message::FeedDownloaded(String::from("Show"))), ..Default::default() };
+            std::fs::write(&path, "\u{feff}<?xml version=\"1.0\"?><rss></rss>").unwrap();
+            assert!(verify(&feed, &path, &response("text/html")).await.is_ok());
+            std::fs::write(&path, "<html><body>Not found</body></html>").unwrap();
+            assert!(verify(&feed, &path, &response("application/rss+xml")).await.is_err());
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn a_pending_file_is_not_queued_twice() {
+            let dl = |state| Download { path: PathBuf::from("show/ep.mp3"), state, ..Default::default() };
+            let mut downloads = ThingList { things: vec![dl(DownloadState::Failed)], ..Default::default() };
+            assert!(downloads.enqueue(dl(DownloadState::Queued)));
+            assert!(!downloads.enqueue(dl(DownloadState::Queued)));
+            downloads.things[1].state = DownloadState::Paused;
+            assert!(!downloads.enqueue(dl(DownloadState::Queued)));
+            downloads.things[1].state = DownloadState::Done;
+            assert!(downloads.enqueue(dl(DownloadState::Queued)));
+            assert_eq!(downloads.things.len(), 3);
+        }
+
+        #[test]
+        fn cancel_removes_only_the_partial_file() {
+            let dir = std::env::temp_dir().join(format!("homily-cancel-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("show.rss");
+            std::fs::write(&path, "old feed").unwrap();
+            std::fs::write(part_path(&path), "half").unwrap();
+            let mut downloads = ThingList {
+                things: vec![Download { path: path.clone(), state: DownloadState::Paused, ..Default::default() }],
+                ..Default::default()
+            };
+            downloads.cancel_current();
+            assert_eq!(downloads.things[0].state, DownloadState::Cancelled);
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "old feed");
+            assert!(!part_path(&path).exists());
+            std::fs::remove_dir_all(&dir).unwrap();
         }
     }
-}
\ No newline at end of file
+}
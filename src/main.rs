@@ -1,38 +1,53 @@
+use homily::artwork::*;
+use homily::chapters::{load_chapters, Chapter};
+use homily::client::build_client;
+use homily::config::Config;
+use homily::error::{HomilyError, Result};
 use homily::general::*;
+use homily::history::*;
+use homily::hooks::{episode_env, run_hooks};
 use homily::keymap::*;
+use homily::mpv;
+use homily::playback::Playback;
+use homily::player::*;
+use homily::playlist::*;
+use homily::ratelimit::*;
+use homily::redact::{self, redact};
+use homily::secrets::Secrets;
+use homily::store::{load_queue, save_queue, SavedDownload, SavedKind};
 use homily::stringlogger::*;
+use homily::tagging::{tag_file, TagInfo};
+use homily::transcript::{format_time, load_transcript, Cue, Search};
 use homily::ui_crossterm::*;
+use homily::upnext::*;
 
-use std::cmp::Ordering;
+use std::cmp::{max, min, Ordering};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use dirs::home_dir;
 use tokio::runtime::Runtime;
-use futures::prelude::*;
-use futures::future::*;
-use futures::{stream, StreamExt};
 use futures_channel::mpsc::{unbounded, UnboundedSender};
 use log::{info, LevelFilter};
-use quick_xml::de::{from_str};
-use quick_xml::DeError;
+use quick_xml::de::{from_str, DeError};
 use serde::Deserialize;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Default, PartialEq)]
 enum View {
+    #[default]
     Feeds,
     Episodes,
     Headers,
     Downloads,
     Log,
-    //Details,
-}
-
-impl Default for View {
-    fn default() -> Self { View::Feeds }
+    History,
+    Details,
+    Transcript,
+    Chapters,
+    UpNext,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -46,46 +61,100 @@ struct FeedList {
     feeds: Vec<Feed>,
 }
 
-fn load_rss(filename: PathBuf) -> Result<Rss, ()> {
-    fs::read_to_string(&filename).map_err(|_| ()).and_then(
-        |r| {
-            from_str(&r
-                .replace("itunes:title", "itunes-title")
-                .replace("& ", "&amp; "))
-            .map_err(
-                |e| {
-                    info!("{:?}", e);
-                    match e {
-                        DeError::Xml(xe) => info!("xml error: {}", xe),
-                        _ => info!("other error"),
-                    };
+/// Moves every `<tag>` element of an item next to the first one. quick-xml only reads a
+/// sequence whose elements are adjacent, and feeds often put a `<guid>` between transcripts.
+fn gather_elements(xml: &str, tag: &str) -> String {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    xml.split_inclusive("</item>").map(|item| {
+        let mut spans = vec![];
+        let mut from = 0;
+        while let Some(start) = item[from..].find(&open).map(|i| i + from) {
+            let after = start + open.len();
+            if !item[after..].starts_with(|c: char| c == '/' || c == '>' || c.is_whitespace()) {
+                from = after;
+                continue;
+            }
+            let tag_end = match item[after..].find('>') {
+                Some(i) => after + i + 1,
+                None => break,
+            };
+            let end = if item[..tag_end].ends_with("/>") {
+                tag_end
+            } else {
+                match item[tag_end..].find(&close) {
+                    Some(i) => tag_end + i + close.len(),
+                    None => break,
                 }
-            )
+            };
+            spans.push((start, end));
+            from = end;
         }
-    )
+        if spans.len() < 2 {
+            return item.to_string();
+        }
+        let gathered: String = spans.iter().map(|&(start, end)| &item[start..end]).collect();
+        let mut result = String::with_capacity(item.len());
+        let mut last = 0;
+        for (i, &(start, end)) in spans.iter().enumerate() {
+            result.push_str(&item[last..start]);
+            if i == 0 {
+                result.push_str(&gathered);
+            }
+            last = end;
+        }
+        result.push_str(&item[last..]);
+        result
+    }).collect()
 }
 
-fn update_feed(f: &mut Feed, config_path: PathBuf) {
-    if let Ok(rss) = load_rss(config_path.join(&f.save_path())) {
-        let feed_rc = Rc::new(f.clone());
-        f.episodes.things = rss.channel.episodes.into_iter().collect();
-        for ep in &mut f.episodes.things {
-            (*ep).feed = Some(feed_rc.clone());
-        }
+/// quick-xml matches elements by their local name, so namespaced elements that share one
+/// with an element we read are renamed first, or the feed fails with a duplicate field.
+fn parse_rss(text: &str) -> std::result::Result<Rss, DeError> {
+    let text = text
+        .replace("itunes:title", "itunes-title")
+        .replace("itunes:image", "itunes-image")
+        .replace("googleplay:image", "googleplay-image")
+        .replace("itunes:episode", "itunes-episode")
+        .replace("itunes:season", "itunes-season")
+        .replace("itunes:description", "itunes-description")
+        .replace("media:description", "media-description")
+        .replace("podcast:transcript", "podcast-transcript")
+        .replace("podcast:chapters", "podcast-chapters")
+        .replace("& ", "&amp; ");
+    from_str(&gather_elements(&text, "podcast-transcript"))
+}
 
-        f.episodes.things.sort_by(
-            |e1, e2|
-            match (e1.pub_date, e2.pub_date) {
-                (Some(date1), Some(date2)) => date2.cmp(&date1),
-                _ => Ordering::Greater,
-            }
-        );
+fn load_rss(filename: PathBuf) -> Result<Rss> {
+    let r = fs::read_to_string(&filename).map_err(|e| HomilyError::io(&filename, e))?;
+    parse_rss(&r).map_err(|e| HomilyError::parse(&filename, e))
+}
 
-        f.check_episodes_downloaded();
-        info!("Loaded RSS: {}", f.name);
-    } else {
-        info!("Failed to load RSS: {}", f.name);
+fn update_feed(f: &mut Feed, config_path: PathBuf) {
+    let rss = match load_rss(config_path.join(f.save_path())) {
+        Ok(rss) => rss,
+        Err(e) => {
+            info!("Failed to load RSS {}: {}", f.name, e);
+            return;
+        },
+    };
+    f.image_url = image_url(rss.channel.itunes_image.as_ref(), rss.channel.image.as_ref());
+    let feed_rc = Rc::new(f.clone());
+    f.episodes.things = rss.channel.episodes.into_iter().collect();
+    for ep in &mut f.episodes.things {
+        ep.feed = Some(feed_rc.clone());
     }
+
+    f.episodes.things.sort_by(
+        |e1, e2|
+        match (e1.pub_date, e2.pub_date) {
+            (Some(date1), Some(date2)) => date2.cmp(&date1),
+            _ => Ordering::Greater,
+        }
+    );
+
+    f.check_episodes_downloaded();
+    info!("Loaded RSS: {}", f.name);
 }
 
 fn log_time() {
@@ -93,51 +162,359 @@ fn log_time() {
     info!("{}", t.as_secs() as u128 * 1000 + t.subsec_millis() as u128);
 }
 
-fn load_feeds(config_path: PathBuf) -> Vec<Feed> {
+fn load_feeds(config_path: PathBuf) -> Result<Vec<Feed>> {
     log_time();
-    let mut feeds = from_str::<FeedList>(&fs::read_to_string(config_path.join("feeds.xml")).unwrap()).unwrap().feeds;
+    let path = config_path.join("feeds.xml");
+    let text = fs::read_to_string(&path).map_err(|e| HomilyError::io(&path, e))?;
+    let mut feeds = from_str::<FeedList>(&text).map_err(|e| HomilyError::parse(&path, e))?.feeds;
     log_time();
     feeds.iter_mut().for_each(
-        |mut f| update_feed(&mut f, config_path.clone())
+        |f| update_feed(f, config_path.clone())
     );
     log_time();
-    feeds
+    Ok(feeds)
+}
+
+/// Rebuilds a download saved by a previous session, taking the headers and expectations from
+/// its feed or episode when those still exist. Anything that was in flight comes back paused.
+fn restore_download(saved: SavedDownload, feeds: &[Feed], config_path: &PathBuf) -> Download {
+    let feed = saved.feed.as_ref().and_then(|name| feeds.iter().find(|feed| &feed.name == name));
+    let kind = saved.kind();
+    let mut dl = match (feed, kind, &saved.episode) {
+        (Some(feed), SavedKind::Episode, Some(title)) => feed.episodes.things.iter()
+            .find(|ep| &ep.name == title)
+            .map(|ep| ep.get_download(None))
+            .unwrap_or_default(),
+        (Some(feed), SavedKind::Feed, _) => feed.get_download(Some(config_path)),
+        (Some(feed), _, _) => Download { headers: feed.request_headers(), ..Default::default() },
+        _ => Download::default(),
+    };
+    dl.id = next_download_id();
+    dl.url = saved.url;
+    dl.path = saved.path;
+    dl.feed_name = saved.feed;
+    if saved.expected_type.is_some() {
+        dl.expected_type = saved.expected_type;
+    }
+    dl.success_message = match (kind, saved.episode, &dl.feed_name) {
+        (SavedKind::Episode, Some(title), _) => Some(Message::EpisodeDownloaded(title)),
+        (SavedKind::Feed, _, Some(name)) => Some(Message::FeedDownloaded(name.clone())),
+        _ => None,
+    };
+    // Progress isn't saved on every chunk, so trust what actually reached the disk.
+    if saved.state != DownloadState::Queued {
+        dl.downloaded_bytes = fs::metadata(part_path(&dl.path)).map(|m| m.len()).unwrap_or(0);
+    }
+    dl.total_bytes = saved.total;
+    dl.state = match saved.state {
+        DownloadState::Failed => DownloadState::Failed,
+        _ => DownloadState::Paused,
+    };
+    dl
+}
+
+fn save_downloads(config_path: &Path, downloads: &[Download]) {
+    if let Err(e) = save_queue(config_path, downloads) {
+        info!("Couldn't save the download queue: {}", e);
+    }
+}
+
+/// The feed's artwork as a download into `cover.<ext>` in its save folder, if it changed.
+fn cover_download(feed: &Feed, artwork: &[SavedArtwork]) -> Option<Download> {
+    if feed.save_folder.is_empty() {
+        return None;
+    }
+    feed.image_url.clone()
+        .and_then(|url| ArtworkJob::new(url, Path::new(&feed.save_folder).join("cover"), Some(feed.name.clone()), artwork))
+        .map(|job| job.into_download(feed.request_headers()))
+}
+
+/// Fetches the episode's artwork, tags the file and runs the hooks, in that order: the tags
+/// can then include the artwork, and hooks that move or copy the file see the tags. When
+/// there is artwork to fetch, its download is returned and the rest waits until it ends.
+fn after_episode_download(runtime: &Runtime, config: &Config, feed: &Feed, ep: &Episode, path: &Path,
+        artwork: &[SavedArtwork]) -> Option<Download> {
+    let episode_artwork = image_url(ep.itunes_image.as_ref(), None)
+        .filter(|url| config.episode_artwork && Some(url) != feed.image_url.as_ref())
+        .and_then(|url| ArtworkJob::new(url, episode_stem(path), Some(feed.name.clone()), artwork));
+    match episode_artwork {
+        Some(mut job) => {
+            job.after = Some(AfterArtwork { episode: ep.name.clone(), path: path.to_path_buf() });
+            Some(job.into_download(feed.request_headers()))
+        },
+        None => {
+            tag_and_run_hooks(runtime, config, feed, ep, path);
+            None
+        },
+    }
+}
+
+/// Carries on with the episode waiting for `dl`, however the artwork download ended.
+fn after_artwork_download(runtime: &Runtime, config: &Config, feeds: &[Feed], dl: &mut Download) {
+    let after = match dl.artwork.as_mut().and_then(|job| job.after.take()) {
+        Some(after) => after,
+        None => return,
+    };
+    let ep = feeds.iter()
+        .find(|feed| Some(&feed.name) == dl.feed_name.as_ref())
+        .and_then(|feed| feed.episodes.things.iter().find(|ep| ep.name == after.episode).map(|ep| (feed, ep)));
+    if let Some((feed, ep)) = ep {
+        tag_and_run_hooks(runtime, config, feed, ep, &after.path);
+    }
+}
+
+fn tag_and_run_hooks(runtime: &Runtime, config: &Config, feed: &Feed, ep: &Episode, path: &Path) {
+    let mut tag_info = Some(TagInfo::new(feed, ep))
+        .filter(|_| feed.tag.unwrap_or(config.tag_episodes));
+    if let (Some(tag_info), Some(cover)) = (tag_info.as_mut(), find_artwork(&episode_stem(path))) {
+        tag_info.cover = Some(cover);
+    }
+    let hooks = config.hooks.iter().chain(feed.hooks.iter()).cloned().collect();
+    let env = episode_env(feed, ep, path);
+    let path = path.to_path_buf();
+    runtime.spawn(async move {
+        if let Some(tag_info) = tag_info {
+            // Rewriting the tags can mean copying the whole file, so keep it off the runtime's
+            // worker threads.
+            let tag_path = path.clone();
+            match tokio::task::spawn_blocking(move || tag_file(&tag_path, &tag_info)).await {
+                Ok(Ok(())) => {},
+                Ok(Err(e)) => info!("{}", e),
+                Err(e) => info!("Tagging {} stopped: {}", path.to_string_lossy(), e),
+            }
+        }
+        run_hooks(hooks, path, env).await;
+    });
+}
+
+/// How a player started by `play` went, as far as homily can tell.
+#[derive(PartialEq)]
+enum PlayerExit {
+    /// mpv reports the position and sends `PlaybackEnded` when it quits.
+    Tracked,
+    /// Another player ran. Most exit successfully when quit early too, so there is no
+    /// telling whether the episode was heard to the end.
+    Untracked,
+    /// The player couldn't be started or failed.
+    Failed,
+}
+
+/// Plays an episode with the configured player and returns a line for the status bar.
+fn play(ta: &TermAdapter, config: &Config, request: &PlayRequest, utx: &UnboundedSender<Message>) -> (String, PlayerExit) {
+    let template = match &config.player {
+        Some(template) => template,
+        None => return (String::from("No player configured (set <player> in config.xml)"), PlayerExit::Failed),
+    };
+    let mut command = match player_command(template, request) {
+        Ok(command) => command,
+        Err(e) => return (e.to_string(), PlayerExit::Failed),
+    };
+    let is_mpv = mpv::is_mpv(&command.get_program().to_string_lossy());
+    // Resume even when the template doesn't place the position itself.
+    if is_mpv && !template.contains("{start}") && request.start.as_secs() > 0 {
+        command.arg(format!("--start={}", request.start.as_secs()));
+    }
+    let tracked = is_mpv && mpv::track(&mut command, request.key.clone(), utx.clone());
+    info!("Playing {} from {}", request.file, format_time(request.start));
+    match config.player_mode {
+        PlayerMode::Detached => match spawn_detached(command) {
+            Ok(()) => (format!("Playing: {}", request.title), if tracked { PlayerExit::Tracked } else { PlayerExit::Untracked }),
+            Err(e) => (format!("Couldn't start the player: {}", e), PlayerExit::Failed),
+        },
+        PlayerMode::Terminal => {
+            ta.suspend();
+            let result = command.status();
+            ta.resume();
+            match result {
+                Ok(status) if status.success() => (format!("Played: {}", request.title),
+                    if tracked { PlayerExit::Tracked } else { PlayerExit::Untracked }),
+                Ok(status) => (format!("The player exited with {}", status), PlayerExit::Failed),
+                Err(e) => (format!("Couldn't start the player: {}", e), PlayerExit::Failed),
+            }
+        },
+    }
+}
+
+fn save_playback(config_path: &Path, playback: &Playback) {
+    if let Err(e) = playback.save(config_path) {
+        info!("Couldn't save playback positions: {}", e);
+    }
+}
+
+fn save_up_next_queue(config_path: &Path, up_next: &[QueuedEpisode]) {
+    if let Err(e) = save_up_next(config_path, up_next) {
+        info!("Couldn't save the Up Next queue: {}", e);
+    }
+}
+
+/// Plays the episode at the top of Up Next and returns a line for the status bar. With mpv,
+/// also returns its key, so the next one can follow once mpv reports it played. Other players
+/// can't say how far they got, so the episode stays queued until it is removed by hand.
+fn play_up_next(ta: &TermAdapter, config: &Config, feeds: &[Feed], up_next: &[QueuedEpisode],
+        utx: &UnboundedSender<Message>) -> (String, Option<String>) {
+    let request = match up_next.first() {
+        Some(queued) => queued.play_request(feeds),
+        None => return (String::from("Up Next is empty (A adds the selected episode, I plays it next)"), None),
+    };
+    match play(ta, config, &request, utx) {
+        (text, PlayerExit::Tracked) => (text, Some(request.key)),
+        (text, PlayerExit::Untracked) => (format!("{} (x removes it from Up Next, p plays the next)", text), None),
+        (text, PlayerExit::Failed) => (text, None),
+    }
+}
+
+fn record_history(config_path: &Path, history: &mut ThingList<HistoryEntry>, dl: &Download) {
+    if dl.artwork.is_some() {
+        return;
+    }
+    history.things.insert(0, HistoryEntry::from_download(dl));
+    history.things.truncate(MAX_HISTORY);
+    history.selected_index = min(history.selected_index, history.things.len() - 1);
+    if let Err(e) = save_history(config_path, &history.things) {
+        info!("Couldn't save the download history: {}", e);
+    }
+}
+
+/// `homily up-next`: prints the Up Next queue as tab-separated feed, title and URL.
+fn print_up_next(config_path: &Path, args: &[String]) -> Result<()> {
+    if !args.is_empty() {
+        return Err(HomilyError::Config(String::from("usage: homily up-next")));
+    }
+    for queued in load_up_next(config_path)? {
+        println!("{}\t{}\t{}", queued.feed, queued.title, queued.url);
+    }
+    Ok(())
+}
+
+/// Writes the episodes of `source` to `path` and returns how many there were.
+fn export_playlist(path: &Path, base: &Path, format: PlaylistFormat, source: &PlaylistSource,
+        feeds: &[Feed], up_next: &[QueuedEpisode]) -> Result<usize> {
+    let entries = playlist_entries(source, feeds, up_next, base)?;
+    write_playlist(path, format, &entries)?;
+    Ok(entries.len())
+}
+
+/// `homily export-playlist [--format m3u8|pls] [--base <dir>] (--feed <name> | --new | --up-next) <file>`:
+/// paths in the playlist are relative to the base folder, by default the one holding the file.
+fn print_export_playlist(config_path: &Path, args: &[String]) -> Result<()> {
+    let usage = || HomilyError::Config(String::from(
+        "usage: homily export-playlist [--format m3u8|pls] [--base <dir>] (--feed <name> | --new | --up-next) <file>"));
+    let (mut format, mut base, mut source, mut file) = (None, None, None, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = Some(args.next().and_then(|name| PlaylistFormat::from_name(name)).ok_or_else(usage)?),
+            "--base" => base = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            "--feed" => source = Some(PlaylistSource::Feed(args.next().ok_or_else(usage)?.clone())),
+            "--new" => source = Some(PlaylistSource::New),
+            "--up-next" => source = Some(PlaylistSource::UpNext),
+            _ if file.is_none() && !arg.starts_with("--") => file = Some(PathBuf::from(arg)),
+            _ => return Err(usage()),
+        }
+    }
+    let (source, file) = match (source, file) {
+        (Some(source), Some(file)) => (source, file),
+        _ => return Err(usage()),
+    };
+    let format = format.unwrap_or_else(|| PlaylistFormat::for_path(&file));
+    let base = base.unwrap_or_else(|| file.parent().map(Path::to_path_buf).unwrap_or_default());
+
+    let mut feeds = load_feeds(config_path.to_path_buf())?;
+    let playback = Playback::load(config_path)?;
+    feeds.iter_mut().for_each(|feed| playback.apply(&mut feed.episodes.things));
+    let up_next = load_up_next(config_path)?;
+    let count = export_playlist(&file, &base, format, &source, &feeds, &up_next)?;
+    println!("Wrote {} episodes to {}", count, file.to_string_lossy());
+    Ok(())
 }
 
-async fn fetch(utx: UnboundedSender<Message>, view_items: Vec<Download>) {
-    stream::iter(view_items)
-        .map(|view_item| async { download(utx.clone(), view_item).await })
-        .buffer_unordered(8)
-        .for_each(|_| async move { info!("Feed updated"); } )
-        .then(|_| {
-            let utx = utx.clone();
-            info!("done");       
-            utx.unbounded_send(Message::FeedUpdated).unwrap();
-            ready::<u32>(0)
-        })
-        .await;
+/// `homily history [--since 7d]`: prints the history, oldest first, without starting the UI.
+fn print_history(config_path: &Path, args: &[String]) -> Result<()> {
+    let since = match args {
+        [] => None,
+        [flag, age] if flag == "--since" => Some(parse_age(age)
+            .and_then(|age| chrono::Local::now().checked_sub_signed(age))
+            .ok_or_else(|| HomilyError::Config(format!("invalid age: {} (try 12h or 7d)", age)))?),
+        _ => return Err(HomilyError::Config(String::from("usage: homily history [--since <age>]"))),
+    };
+    // Only the secret values matter for masking, so the feeds needn't be loaded.
+    redact::init(Arc::new(Secrets::load(config_path, &[])?), Config::load(config_path)?.reveal_secrets);
+    for entry in load_history(config_path)?.iter().rev() {
+        if since.is_none_or(|since| entry.started >= since) {
+            println!("{}", entry);
+        }
+    }
+    Ok(())
 }
 
 fn main() {
-    let mut config_path = home_dir().unwrap();
+    if let Err(e) = run() {
+        eprintln!("homily: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let mut config_path = home_dir()
+        .ok_or_else(|| HomilyError::Config(String::from("couldn't find the home directory")))?;
     config_path.push(".homily");
 
-    let runtime = Runtime::new().unwrap();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("history") => return print_history(&config_path, &args[1..]),
+        Some("up-next") => return print_up_next(&config_path, &args[1..]),
+        Some("export-playlist") => return print_export_playlist(&config_path, &args[1..]),
+        Some(command) => return Err(HomilyError::Config(format!("unknown command: {}", command))),
+        None => {},
+    }
+
+    let runtime = Runtime::new().map_err(|e| HomilyError::Startup(format!("runtime: {}", e)))?;
     let (utx, mut urx) = unbounded::<Message>();
 
     log::set_boxed_logger(Box::new(
             StringLogger { utx: Mutex::new(utx.clone()) }
         ))
-        .map(|()| log::set_max_level(LevelFilter::Info)).unwrap();
+        .map(|()| log::set_max_level(LevelFilter::Info))
+        .map_err(|e| HomilyError::Startup(format!("logger: {}", e)))?;
 
-    let mut feeds = ThingList { things: load_feeds(config_path.clone()), ..Default::default() };
+    let config = Config::load(&config_path)?;
+    let mut feeds = ThingList { things: load_feeds(config_path.clone())?, ..Default::default() };
+    let mut playback = Playback::load(&config_path)?;
+    feeds.things.iter_mut().for_each(|feed| playback.apply(&mut feed.episodes.things));
+    let secrets = Arc::new(Secrets::load(&config_path, &feeds.things)?);
+    redact::init(secrets.clone(), config.reveal_secrets);
+    let limiter = Arc::new(RateLimiter::new(config.rate_limit, config.rate_schedule.clone()));
+    let context = DownloadContext {
+        utx: utx.clone(),
+        client: build_client(&config)?,
+        limiter: limiter.clone(),
+        secrets: secrets.clone(),
+        read_timeout: Some(config.read_timeout).filter(|&secs| secs > 0).map(Duration::from_secs),
+    };
     let mut headers = ThingList { ..Default::default() };
-    let mut downloads = ThingList { ..Default::default() };
+    let mut downloads = ThingList {
+        things: load_queue(&config_path)?.into_iter()
+            .map(|saved| restore_download(saved, &feeds.things, &config_path))
+            .collect::<Vec<_>>(),
+        ..Default::default()
+    };
     let mut log_messages = ThingList { ..Default::default() };
+    let mut artwork = load_artwork(&config_path)?;
+    let mut details: ThingList<String> = ThingList { ..Default::default() };
+    let mut transcript: ThingList<Cue> = ThingList { ..Default::default() };
+    let mut search = Search::default();
+    let mut chapters: ThingList<Chapter> = ThingList { ..Default::default() };
+    let mut history = ThingList { things: load_history(&config_path)?, ..Default::default() };
+    let mut up_next = ThingList { things: load_up_next(&config_path)?, ..Default::default() };
+    let mut up_next_playing: Option<String> = None;
     let mut dtlist: ThingList<Thing> = ThingList { things: get_things(&feeds.things), ..Default::default() };
     let mut selected_view = View::Feeds;
 
     let mut status = Status("".to_string());
+    let unfinished = downloads.things.iter().filter(|dl| dl.state == DownloadState::Paused).count();
+    if unfinished > 0 {
+        status.0 = format!("Restored {} unfinished downloads (o to view them, then u to resume or U to resume all)", unfinished);
+    }
 
     let update_status = |feeds_local: &mut ThingList<Feed>, status: &mut Status, selected_view: &View| {
         match selected_view {
@@ -162,7 +539,8 @@ fn main() {
     }
 
     loop {
-        if let Some(k) = ta.peek_key() {
+        let key = if search.editing { ta.peek_text_key() } else { ta.peek_key() };
+        if let Some(k) = key {
             (width, height) = ta.size();
 
             let mut update_selected_index = |dtlist: &ThingList<Thing>| {
@@ -172,6 +550,11 @@ fn main() {
                     View::Headers => headers.selected_index = dtlist.selected_index,
                     View::Downloads => downloads.selected_index = dtlist.selected_index,
                     View::Log => log_messages.selected_index = dtlist.selected_index,
+                    View::History => history.selected_index = dtlist.selected_index,
+                    View::Details => details.selected_index = dtlist.selected_index,
+                    View::Transcript => transcript.selected_index = dtlist.selected_index,
+                    View::Chapters => chapters.selected_index = dtlist.selected_index,
+                    View::UpNext => up_next.selected_index = dtlist.selected_index,
                 }
             };
 
@@ -180,9 +563,37 @@ fn main() {
                 update_selected_index(&dtlist);
             };
 
+            // Only moving around shows the selected URL, so messages from other keys stay visible.
+            let previous_view = selected_view;
+            let moved = !search.editing && matches!(k, KeyMap::Up | KeyMap::Down | KeyMap::Home | KeyMap::End);
             update_required = true;
             match k {
-                KeyMap::Quit => break,
+                KeyMap::Input(_) | KeyMap::Backspace | KeyMap::Enter | KeyMap::Cancel if search.editing => {
+                    match k {
+                        KeyMap::Input(c) => search.query.push(c),
+                        KeyMap::Backspace => { search.query.pop(); },
+                        KeyMap::Cancel => search.query.clear(),
+                        _ => {},
+                    }
+                    search.editing = matches!(k, KeyMap::Input(_) | KeyMap::Backspace);
+                    let count = search.mark(&mut transcript.things);
+                    let target = Search::find(&transcript.things, search.origin, true).unwrap_or(search.origin);
+                    transcript.selected_index = target;
+                    dtlist.things = get_things(&transcript.things);
+                    dtlist.selected_index = target;
+                    status.0 = if search.editing {
+                        format!("/{} ({} matches)", search.query, count)
+                    } else if count > 0 {
+                        format!("{} matches for '{}' (n/N for next/previous)", count, search.query)
+                    } else {
+                        String::new()
+                    };
+                },
+                KeyMap::Quit => {
+                    save_downloads(&config_path, &downloads.things);
+                    save_playback(&config_path, &playback);
+                    break;
+                },
                 KeyMap::Up => nav_list(-1),
                 KeyMap::Down => nav_list(1),
                 KeyMap::Home => {
@@ -196,62 +607,259 @@ fn main() {
                 KeyMap::Left | KeyMap::Feeds => switch_view(&mut dtlist, &mut selected_view, View::Feeds, &feeds),
                 KeyMap::Downloads => switch_view(&mut dtlist, &mut selected_view, View::Downloads, &downloads),
                 KeyMap::Right | KeyMap::Enter => {
-                    if selected_view == View::Feeds && feeds.current().episodes.things.len() > 0 {
+                    if selected_view == View::Feeds && !feeds.current().episodes.things.is_empty() {
                         dtlist.things = get_things(&feeds.current().episodes.things);
                         dtlist.selected_index = feeds.current().episodes.selected_index;
                         selected_view = View::Episodes;
+                    } else if selected_view == View::Chapters && !chapters.things.is_empty() {
+                        let request = PlayRequest::new(feeds.current().episodes.current(), chapters.current().start);
+                        status.0 = play(&ta, &config, &request, &utx).0;
                     }
                 },
                 KeyMap::Refresh => {
-                    let mut feed_downloads: Vec<Download> = vec![];
                     for feed in feeds.things.iter() {
                         let mut feed_dl = feed.get_download(Some(&config_path));
                         feed_dl.success_message = Some(Message::FeedDownloaded(feed.name.clone()));
-                        feed_downloads.push(feed_dl.clone());
-                        downloads.things.push(feed_dl);
+                        downloads.enqueue(feed_dl);
                     }
-                    runtime.spawn(fetch(utx.clone(), feed_downloads));
+                    downloads.start_queued(&runtime, &context, &config);
+                    save_downloads(&config_path, &downloads.things);
                 }
                 KeyMap::Episodes => switch_view(&mut dtlist, &mut selected_view, View::Episodes, &(feeds.current().episodes)),
                 KeyMap::Log => switch_view(&mut dtlist, &mut selected_view, View::Log, &log_messages),
+                KeyMap::History => switch_view(&mut dtlist, &mut selected_view, View::History, &history),
+                KeyMap::UpNext => switch_view(&mut dtlist, &mut selected_view, View::UpNext, &up_next),
+                KeyMap::AddUpNext | KeyMap::PlayNext => {
+                    if (selected_view != View::Episodes && selected_view != View::Details)
+                            || feeds.current().episodes.things.is_empty() {
+                        continue;
+                    }
+                    let feed = feeds.current();
+                    let queued = QueuedEpisode::new(feed, &feed.episodes.things[feed.episodes.selected_index]);
+                    // The episode at the top stays put while it is playing.
+                    let index = match k {
+                        KeyMap::PlayNext => Some(if up_next_playing.is_some() { 1 } else { 0 }),
+                        _ => None,
+                    };
+                    let title = queued.title.clone();
+                    up_next.add(queued, index);
+                    status.0 = format!("Up Next: {} ({} queued)", title, up_next.things.len());
+                    save_up_next_queue(&config_path, &up_next.things);
+                },
+                KeyMap::ExportPlaylist => {
+                    let (source, name) = match selected_view {
+                        View::Feeds | View::Episodes => (PlaylistSource::Feed(feeds.current().name.clone()), feeds.current().folder.clone()),
+                        View::UpNext => (PlaylistSource::UpNext, String::from("up-next")),
+                        _ => (PlaylistSource::New, String::from("new")),
+                    };
+                    let folder = config.playlist_folder.as_ref().map(PathBuf::from)
+                        .unwrap_or_else(|| config_path.join("playlists"));
+                    let path = folder.join(format!("{}.{}", name, config.playlist_format.extension()));
+                    status.0 = match export_playlist(&path, &folder, config.playlist_format, &source, &feeds.things, &up_next.things) {
+                        Ok(count) => format!("Wrote {} episodes to {}", count, path.to_string_lossy()),
+                        Err(e) => e.to_string(),
+                    };
+                },
+                KeyMap::Play if selected_view == View::UpNext => {
+                    (status.0, up_next_playing) = play_up_next(&ta, &config, &feeds.things, &up_next.things, &utx);
+                    switch_view(&mut dtlist, &mut selected_view, View::UpNext, &up_next);
+                },
+                KeyMap::MoveUp | KeyMap::MoveDown | KeyMap::Cancel if selected_view == View::UpNext => {
+                    match k {
+                        KeyMap::MoveUp => up_next.move_current(-1),
+                        KeyMap::MoveDown => up_next.move_current(1),
+                        _ => up_next.remove_current(),
+                    }
+                    save_up_next_queue(&config_path, &up_next.things);
+                    switch_view(&mut dtlist, &mut selected_view, View::UpNext, &up_next);
+                },
+                KeyMap::Details => {
+                    if selected_view != View::Episodes || feeds.current().episodes.things.is_empty() {
+                        continue;
+                    }
+                    details = ThingList { things: feeds.current().episodes.current().details(), ..Default::default() };
+                    switch_view(&mut dtlist, &mut selected_view, View::Details, &details);
+                },
+                KeyMap::Transcript => {
+                    if (selected_view != View::Episodes && selected_view != View::Details)
+                            || feeds.current().episodes.things.is_empty() {
+                        continue;
+                    }
+                    let ep = feeds.current().episodes.current();
+                    let path = ep.transcripts.iter()
+                        .map(|t| ep.transcript_path(t))
+                        .find(|path| path.exists());
+                    match path.map(|path| load_transcript(&path)) {
+                        Some(Ok(cues)) => {
+                            transcript = ThingList { things: cues, ..Default::default() };
+                            search = Search::default();
+                            switch_view(&mut dtlist, &mut selected_view, View::Transcript, &transcript);
+                        },
+                        Some(Err(e)) => status.0 = e.to_string(),
+                        None if ep.transcripts.is_empty() => status.0 = String::from("This episode has no transcript"),
+                        None => status.0 = String::from("The transcript hasn't been downloaded (see download-transcripts)"),
+                    }
+                },
+                KeyMap::Chapters => {
+                    if (selected_view != View::Episodes && selected_view != View::Details)
+                            || feeds.current().episodes.things.is_empty() {
+                        continue;
+                    }
+                    match load_chapters(feeds.current().episodes.current()) {
+                        Ok(list) if list.is_empty() => status.0 = String::from("No chapters (download the episode or its chapters file first)"),
+                        Ok(list) => {
+                            chapters = ThingList { things: list, ..Default::default() };
+                            switch_view(&mut dtlist, &mut selected_view, View::Chapters, &chapters);
+                        },
+                        Err(e) => status.0 = e.to_string(),
+                    }
+                },
+                KeyMap::Play => {
+                    if (selected_view != View::Episodes && selected_view != View::Details)
+                            || feeds.current().episodes.things.is_empty() {
+                        continue;
+                    }
+                    let ep = feeds.current().episodes.current();
+                    let request = PlayRequest::new(ep, ep.resume_position());
+                    status.0 = play(&ta, &config, &request, &utx).0;
+                },
+                KeyMap::TogglePlayed | KeyMap::MarkFeedPlayed | KeyMap::MarkOlderPlayed => {
+                    let feed = feeds.current();
+                    if feed.episodes.things.is_empty() {
+                        continue;
+                    }
+                    let selected = feed.episodes.selected_index;
+                    let changes: Vec<(String, bool)> = match (k, selected_view) {
+                        (KeyMap::TogglePlayed, View::Episodes) => {
+                            let ep = feed.episodes.current();
+                            vec![(ep.key(), !ep.played)]
+                        },
+                        (KeyMap::MarkFeedPlayed, View::Feeds) | (KeyMap::MarkFeedPlayed, View::Episodes) =>
+                            feed.episodes.things.iter().map(|ep| (ep.key(), true)).collect(),
+                        // Episodes are sorted newest first, so the older ones come after the selection.
+                        (KeyMap::MarkOlderPlayed, View::Episodes) =>
+                            feed.episodes.things[selected + 1..].iter().map(|ep| (ep.key(), true)).collect(),
+                        _ => continue,
+                    };
+                    for (key, played) in &changes {
+                        playback.set_played(key, *played);
+                    }
+                    playback.apply(&mut feed.episodes.things);
+                    save_playback(&config_path, &playback);
+                    status.0 = format!("{} unplayed in {}", feed.unplayed(), feed.name);
+                    if up_next.remove_played(&playback) {
+                        save_up_next_queue(&config_path, &up_next.things);
+                    }
+                    match selected_view {
+                        View::Feeds => switch_view(&mut dtlist, &mut selected_view, View::Feeds, &feeds),
+                        _ => switch_view(&mut dtlist, &mut selected_view, View::Episodes, &feeds.current().episodes),
+                    }
+                },
+                KeyMap::Search if selected_view == View::Transcript => {
+                    search = Search { editing: true, origin: transcript.selected_index, ..Default::default() };
+                    search.mark(&mut transcript.things);
+                    status.0 = String::from("/");
+                },
+                KeyMap::NextMatch | KeyMap::PrevMatch if selected_view == View::Transcript && !transcript.things.is_empty() => {
+                    let forward = matches!(k, KeyMap::NextMatch);
+                    let from = if forward { transcript.selected_index + 1 } else { transcript.selected_index + transcript.things.len() - 1 };
+                    if let Some(target) = Search::find(&transcript.things, from, forward) {
+                        transcript.selected_index = target;
+                        dtlist.selected_index = target;
+                    }
+                },
                 KeyMap::Headers => { 
-                    let header_url: Option<String> = match selected_view {
-                        View::Feeds => Some(feeds.current().url.clone()),
-                        View::Episodes => Some(feeds.current().episodes.current().enclosure.url.clone()),
+                    let header_request = match selected_view {
+                        View::Feeds => Some(feeds.current().get_download(None)),
+                        View::Episodes => Some(feeds.current().episodes.current().get_download(None)),
                         _ => None,
                     };
-                    if let Some(url) = header_url {
-                        runtime.spawn(head(utx.clone(), url));
+                    if let Some(dl) = header_request {
+                        runtime.spawn(head(context.clone(), dl));
                     }
                     switch_view(&mut dtlist, &mut selected_view, View::Headers, &headers);
                 },
                 KeyMap::Download => { 
-                    let (name, sp, dl) = match selected_view {
+                    let (name, mut dl) = match selected_view {
                         View::Episodes => (
                             feeds.current().episodes.current().name.clone(),
-                            feeds.current().episodes.current().save_path().clone(),
                             feeds.current().episodes.current().get_download(None),
                         ),
                         View::Feeds => (
                             feeds.current().name.clone(),
-                            feeds.current().save_path().clone(),
                             feeds.current().get_download(Some(&config_path)),
                         ),
                         _ => continue,
                     };
-                    let msg = match selected_view {
-                        View::Episodes => Message::EpisodeDownloaded(name),
-                        View::Feeds => Message::FeedDownloaded(name),
-                        _ => Message::FeedDownloaded(name),
+                    dl.success_message = match selected_view {
+                        View::Episodes => Some(Message::EpisodeDownloaded(name)),
+                        _ => Some(Message::FeedDownloaded(name)),
+                    };
+                    let path = dl.path.clone();
+                    if !downloads.enqueue(dl) {
+                        status.0 = format!("Already in the download queue: {}", path.to_string_lossy());
+                    } else if selected_view == View::Episodes {
+                        for extra in feeds.current().episodes.current()
+                                .extra_downloads(config.download_transcripts, config.download_chapters) {
+                            downloads.enqueue(extra);
+                        }
+                    }
+                    downloads.start_queued(&runtime, &context, &config);
+                    save_downloads(&config_path, &downloads.things);
+                },
+                KeyMap::Retry if selected_view == View::Episodes => {
+                    let mut dl = feeds.current().episodes.current().get_download(None);
+                    dl.success_message = Some(Message::EpisodeDownloaded(feeds.current().episodes.current().name.clone()));
+                    let path = dl.path.clone();
+                    if !downloads.enqueue(dl) {
+                        status.0 = format!("Already in the download queue: {}", path.to_string_lossy());
+                    } else {
+                        for extra in feeds.current().episodes.current()
+                                .extra_downloads(config.download_transcripts, config.download_chapters) {
+                            downloads.enqueue(extra);
+                        }
+                    }
+                    downloads.start_queued(&runtime, &context, &config);
+                    save_downloads(&config_path, &downloads.things);
+                },
+                KeyMap::Pause | KeyMap::Resume | KeyMap::ResumeAll | KeyMap::Cancel | KeyMap::Retry |
+                KeyMap::MoveUp | KeyMap::MoveDown | KeyMap::ClearFinished => {
+                    if selected_view != View::Downloads {
+                        continue;
+                    }
+                    match k {
+                        KeyMap::Pause => downloads.pause_current(),
+                        KeyMap::Resume => downloads.resume_current(),
+                        KeyMap::ResumeAll => downloads.resume_all(),
+                        KeyMap::Cancel => {
+                            let was_cancelled = downloads.things.is_empty() || downloads.current().state == DownloadState::Cancelled;
+                            downloads.cancel_current();
+                            if !was_cancelled && downloads.current().state == DownloadState::Cancelled {
+                                record_history(&config_path, &mut history, downloads.current());
+                                after_artwork_download(&runtime, &config, &feeds.things, downloads.current());
+                            }
+                        },
+                        KeyMap::Retry => downloads.retry_current(),
+                        KeyMap::MoveUp => downloads.move_current(-1),
+                        KeyMap::MoveDown => downloads.move_current(1),
+                        _ => downloads.clear_finished(),
+                    }
+                    downloads.start_queued(&runtime, &context, &config);
+                    save_downloads(&config_path, &downloads.things);
+                    switch_view(&mut dtlist, &mut selected_view, View::Downloads, &downloads);
+                },
+                KeyMap::RateDown | KeyMap::RateUp => {
+                    if selected_view != View::Downloads {
+                        continue;
+                    }
+                    let limit = match (k, limiter.current_limit()) {
+                        (KeyMap::RateDown, None) => Some(1000 * 1000),
+                        (KeyMap::RateDown, Some(limit)) => Some(max(limit / 2, 10 * 1000)),
+                        (_, Some(limit)) if limit < 100 * 1000 * 1000 => Some(limit * 2),
+                        _ => None,
                     };
-                    let utx = utx.clone();
-                    runtime.spawn(download(utx.clone(), dl.clone())
-                    .then(move |_| {
-                        info!("Downloaded {}", sp);
-                        utx.unbounded_send(msg).unwrap();
-                        ready::<u32>(0)
-                    }));
-                    downloads.things.push(dl);
+                    limiter.set_limit(limit);
+                    status.0 = format!("Rate limit: {}", rate_pretty(limit));
                 },
                 KeyMap::Resize(w, h) => {
                     width = w;
@@ -259,19 +867,23 @@ fn main() {
                 },
                 _ => { update_required = false },
             }
+            if moved || selected_view != previous_view {
+                update_status(&mut feeds, &mut status, &selected_view);
+            }
         }
 
-        while let Ok(Some(val)) = urx.try_next() {
+        while let Ok(val) = urx.try_recv() {
             match val {
-                Message::Notification(text) => status.0 = format!("{}", text),
-                Message::FeedUpdated => {
-                    status.0 = format!("Feed updated");
-                    feeds.things = load_feeds(config_path.clone());
-                },
+                Message::Notification(text) => status.0 = text.to_string(),
                 Message::FeedDownloaded(feedname) => {
                     status.0 = format!("Downloaded: {}", feedname);
-                    if let Some(mut feed) = feeds.things.iter_mut().find(|feed| feed.name == feedname) {
-                        update_feed(&mut feed, config_path.clone());
+                    if let Some(feed) = feeds.things.iter_mut().find(|feed| feed.name == feedname) {
+                        update_feed(feed, config_path.clone());
+                        playback.apply(&mut feed.episodes.things);
+                        if let Some(dl) = cover_download(feed, &artwork) {
+                            downloads.enqueue(dl);
+                            downloads.start_queued(&runtime, &context, &config);
+                        }
                         info!("Downloaded feed: {}", feed.name);
                     }
                     if let View::Feeds = selected_view {
@@ -287,31 +899,122 @@ fn main() {
                     }
                     update_required = true;
                 },
+                Message::ArtworkSaved(saved) => {
+                    artwork.retain(|old| old.stem != saved.stem);
+                    artwork.push(saved);
+                    if let Err(e) = save_artwork(&config_path, &artwork) {
+                        info!("Couldn't save the artwork list: {}", e);
+                    }
+                },
+                Message::PlaybackPosition { key, position, duration } => {
+                    if playback.update(&key, position, duration, config.played_threshold) {
+                        info!("Played: {}", key);
+                        if up_next.remove_played(&playback) {
+                            save_up_next_queue(&config_path, &up_next.things);
+                        }
+                    }
+                    feeds.things.iter_mut().for_each(|feed| playback.apply(&mut feed.episodes.things));
+                    if let View::Episodes = selected_view {
+                        dtlist.things = get_things(&feeds.current().episodes.things);
+                        update_required = true;
+                    }
+                },
+                Message::PlaybackEnded(key) => {
+                    save_playback(&config_path, &playback);
+                    // Carry on through Up Next only if the episode was heard to the end.
+                    if up_next_playing.as_ref() == Some(&key) {
+                        up_next_playing = None;
+                        if playback.get(&key).is_some_and(|p| p.played) && !up_next.things.is_empty() {
+                            (status.0, up_next_playing) = play_up_next(&ta, &config, &feeds.things, &up_next.things, &utx);
+                        }
+                    }
+                },
                 Message::Headers(headers_list) => {
                     selected_view = View::Headers;
                     headers.things = headers_list;
                 }
                 Message::DownloadProgress(id, progress) => {
-                    if let Some(dl) = downloads.things.iter_mut().find(|x| x.url == id) {
+                    if let Some(dl) = downloads.find(id) {
                         dl.downloaded_bytes = progress;
                     }
                 },
                 Message::DownloadSize(id, progress) => {
-                    if let Some(dl) = downloads.things.iter_mut().find(|x| x.url == id) {
+                    if let Some(dl) = downloads.find(id) {
                         dl.total_bytes = progress;
                     }
+                    save_downloads(&config_path, &downloads.things);
+                },
+                Message::DownloadRetry(id, retries) => {
+                    if let Some(dl) = downloads.find(id) {
+                        dl.retries = retries;
+                    }
+                },
+                Message::DownloadDone(id) => {
+                    let mut artwork_dl = None;
+                    if let Some(dl) = downloads.find(id) {
+                        dl.state = DownloadState::Done;
+                        dl.abort_handle = None;
+                        info!("Download done: {}", dl.path.to_string_lossy());
+                        record_history(&config_path, &mut history, dl);
+                        if let Some(Message::EpisodeDownloaded(title)) = &dl.success_message {
+                            let feed = feeds.things.iter().find(|feed| Some(&feed.name) == dl.feed_name.as_ref());
+                            if let Some(feed) = feed {
+                                if let Some(ep) = feed.episodes.things.iter().find(|ep| &ep.name == title) {
+                                    artwork_dl = after_episode_download(&runtime, &config, feed, ep, &dl.path, &artwork);
+                                }
+                            }
+                        }
+                        after_artwork_download(&runtime, &config, &feeds.things, dl);
+                    }
+                    if let Some(mut dl) = artwork_dl {
+                        // The same picture is already on its way; don't hold the episode back.
+                        if downloads.has_pending(&dl.path) {
+                            after_artwork_download(&runtime, &config, &feeds.things, &mut dl);
+                        } else {
+                            downloads.enqueue(dl);
+                        }
+                    }
+                    downloads.start_queued(&runtime, &context, &config);
+                    save_downloads(&config_path, &downloads.things);
+                },
+                Message::DownloadFailed { id, error } => {
+                    if let Some(dl) = downloads.find(id) {
+                        dl.state = DownloadState::Failed;
+                        dl.abort_handle = None;
+                        status.0 = format!("Download failed: {}: {} (a to retry)",
+                            dl.path.file_name().unwrap_or_default().to_string_lossy(), error);
+                        dl.error = Some(error);
+                        record_history(&config_path, &mut history, dl);
+                        after_artwork_download(&runtime, &config, &feeds.things, dl);
+                    }
+                    save_downloads(&config_path, &downloads.things);
+                    feeds.things.iter_mut().for_each(|feed| feed.check_episodes_downloaded());
+                    if let View::Episodes = selected_view {
+                        dtlist.things = get_things(&feeds.current().episodes.things);
+                    }
+                    downloads.start_queued(&runtime, &context, &config);
                 },
                 Message::LogMessage(text) => log_messages.things.push(text),
             }
             status_update_required = true;
+            if selected_view == View::Downloads {
+                dtlist.things = get_things(&downloads.things);
+                update_required = true;
+            } else if selected_view == View::History {
+                dtlist.things = get_things(&history.things);
+                update_required = true;
+            } else if selected_view == View::UpNext {
+                switch_view(&mut dtlist, &mut selected_view, View::UpNext, &up_next);
+                update_required = true;
+            }
         }
 
         if update_required {
-            update_status(&mut feeds, &mut status, &selected_view);
             ta.update(&dtlist, &status, height, width);
         }
 
         if update_required || status_update_required {
+            status.0 = redact(&status.0);
             ta.update_status(&dtlist, &status, height, width);
         }
 
@@ -320,4 +1023,81 @@ fn main() {
     }
 
     ta.clear();
-}
\ No newline at end of file
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rss(items: &str) -> String {
+        format!("<rss><channel><title>Show</title>{}</channel></rss>", items)
+    }
+
+    fn item(extra: &str) -> String {
+        format!("<item><title>One</title><pubDate>Mon, 01 Jan 2024 10:00:00 +0000</pubDate>\
+            <enclosure url=\"http://example.com/1.mp3\" length=\"10\" type=\"audio/mpeg\"/>{}</item>", extra)
+    }
+
+    #[test]
+    fn itunes_and_podcast_numbers_do_not_collide() {
+        let parsed = parse_rss(&rss(&item("<itunes:episode>3</itunes:episode><podcast:episode>3</podcast:episode>\
+            <itunes:season>2</itunes:season><podcast:season>2</podcast:season>"))).unwrap();
+        let ep = &parsed.channel.episodes[0];
+        assert_eq!(ep.episode_number, Some(3));
+        assert_eq!(ep.season, Some(2));
+    }
+
+    #[test]
+    fn googleplay_image_does_not_collide() {
+        let parsed = parse_rss(&format!("<rss><channel><title>Show</title>\
+            <image><url>http://example.com/rss.jpg</url></image>\
+            <googleplay:image href=\"http://example.com/gp.jpg\"/>\
+            <itunes:image href=\"http://example.com/it.jpg\"/>{}</channel></rss>", item(""))).unwrap();
+        assert_eq!(parsed.channel.image.unwrap().url.as_deref(), Some("http://example.com/rss.jpg"));
+        assert_eq!(parsed.channel.itunes_image.unwrap().href.as_deref(), Some("http://example.com/it.jpg"));
+        assert_eq!(parsed.channel.episodes.len(), 1);
+    }
+
+    #[test]
+    fn transcripts_need_not_be_adjacent() {
+        let parsed = parse_rss(&rss(&format!("{}{}",
+            item("<podcast:transcript url=\"http://example.com/1.vtt\" type=\"text/vtt\"/>\
+                <guid>one</guid>\
+                <podcast:transcript url=\"http://example.com/1.srt\" type=\"application/srt\"></podcast:transcript>\
+                <podcast:chapters url=\"http://example.com/1.json\" type=\"application/json+chapters\"/>\
+                <podcast:transcript url=\"http://example.com/1.json\" type=\"application/json\"/>"),
+            item("<podcast:transcript url=\"http://example.com/2.vtt\" type=\"text/vtt\"/>")))).unwrap();
+        let urls: Vec<&str> = parsed.channel.episodes[0].transcripts.iter().map(|t| t.url.as_str()).collect();
+        assert_eq!(urls, ["http://example.com/1.vtt", "http://example.com/1.srt", "http://example.com/1.json"]);
+        assert_eq!(parsed.channel.episodes[0].guid.as_ref().unwrap().value, "one");
+        assert!(parsed.channel.episodes[0].chapters.is_some());
+        assert_eq!(parsed.channel.episodes[1].transcripts.len(), 1);
+    }
+
+    #[test]
+    fn transcripts_without_a_type_use_the_url() {
+        let parsed = parse_rss(&rss(&item("<podcast:transcript url=\"http://example.com/1.srt?x=1\"/>\
+            <podcast:transcript url=\"http://example.com/1\"/>"))).unwrap();
+        let extensions: Vec<&str> = parsed.channel.episodes[0].transcripts.iter().map(|t| t.extension()).collect();
+        assert_eq!(extensions, ["srt", "txt"]);
+    }
+
+    #[test]
+    fn odd_episode_numbers_are_ignored() {
+        let parsed = parse_rss(&rss(&format!("{}{}",
+            item("<itunes:episode></itunes:episode><itunes:season>Bonus</itunes:season>"),
+            item("<itunes:episode>Bonus</itunes:episode>")))).unwrap();
+        assert_eq!(parsed.channel.episodes.len(), 2);
+        assert_eq!(parsed.channel.episodes[0].episode_number, None);
+        assert_eq!(parsed.channel.episodes[0].season, None);
+        assert_eq!(parsed.channel.episodes[1].episode_number, None);
+    }
+
+    #[test]
+    fn namespaced_descriptions_do_not_collide() {
+        let parsed = parse_rss(&rss(&item("<description>Plain</description>\
+            <media:description>Media</media:description><itunes:description>iTunes</itunes:description>"))).unwrap();
+        assert_eq!(parsed.channel.episodes[0].description.as_deref(), Some("Plain"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::process::Command;
+
+use futures_channel::mpsc::UnboundedSender;
+
+use crate::general::Message;
+
+/// True when the player command starts mpv, which can report its position over IPC.
+pub fn is_mpv(program: &str) -> bool {
+    Path::new(program).file_stem().is_some_and(|name| name == "mpv")
+}
+
+/// Has the mpv started by `command` listen on a socket of its own and follows its playback
+/// position, sending `Message::PlaybackPosition` about once a second and
+/// `Message::PlaybackEnded` when mpv quits. Returns false where mpv can't be followed, as the
+/// IPC socket is a Unix socket.
+pub fn track(command: &mut Command, key: String, utx: UnboundedSender<Message>) -> bool {
+    #[cfg(unix)]
+    {
+        ipc::track(command, key, utx);
+        true
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (command, key, utx);
+        false
+    }
+}
+
+#[cfg(unix)]
+mod ipc {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use futures_channel::mpsc::UnboundedSender;
+    use log::info;
+    use serde_json::Value;
+
+    use crate::general::Message;
+
+    static NEXT_SOCKET: AtomicU64 = AtomicU64::new(0);
+
+    /// A fresh socket path for one mpv instance, so detached players don't share one.
+    fn socket_path() -> PathBuf {
+        std::env::temp_dir().join(format!("homily-mpv-{}-{}.sock",
+            std::process::id(), NEXT_SOCKET.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    pub fn track(command: &mut Command, key: String, utx: UnboundedSender<Message>) {
+        let socket = socket_path();
+        command.arg(format!("--input-ipc-server={}", socket.to_string_lossy()));
+        thread::spawn(move || {
+            let stream = connect(&socket);
+            let mut position = None;
+            let mut duration = None;
+            if let Some(stream) = stream {
+                let mut writer = &stream;
+                let observe = "{\"command\":[\"observe_property\",1,\"time-pos\"]}\n\
+                    {\"command\":[\"observe_property\",2,\"duration\"]}\n";
+                if let Err(e) = writer.write_all(observe.as_bytes()) {
+                    info!("Couldn't talk to mpv: {}", e);
+                }
+                let mut last_sent = Instant::now();
+                for line in BufReader::new(&stream).lines() {
+                    let event: Value = match line.ok().and_then(|line| serde_json::from_str(&line).ok()) {
+                        Some(event) => event,
+                        None => break,
+                    };
+                    if event["event"] != "property-change" {
+                        continue;
+                    }
+                    match event["name"].as_str() {
+                        Some("time-pos") => position = event["data"].as_f64().or(position),
+                        Some("duration") => duration = event["data"].as_f64().or(duration),
+                        _ => {},
+                    }
+                    if let Some(position) = position.filter(|_| last_sent.elapsed() >= Duration::from_secs(1)) {
+                        let _ = utx.unbounded_send(Message::PlaybackPosition { key: key.clone(), position, duration });
+                        last_sent = Instant::now();
+                    }
+                }
+            }
+            if let Some(position) = position {
+                let _ = utx.unbounded_send(Message::PlaybackPosition { key: key.clone(), position, duration });
+            }
+            let _ = utx.unbounded_send(Message::PlaybackEnded(key));
+            let _ = std::fs::remove_file(&socket);
+        });
+    }
+
+    /// mpv creates the socket a moment after it starts, so keep trying for a few seconds.
+    fn connect(socket: &Path) -> Option<UnixStream> {
+        for _ in 0..50 {
+            if let Ok(stream) = UnixStream::connect(socket) {
+                return Some(stream);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        info!("Couldn't connect to mpv at {}", socket.to_string_lossy());
+        None
+    }
+}
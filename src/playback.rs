@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::Result;
+use crate::general::Episode;
+use crate::store::{read_records, write_records, Record};
+
+/// How far an episode has been listened to, kept in `playback.xml`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Progress {
+    /// `Episode::key` of the episode.
+    pub key: String,
+    /// Seconds into the episode.
+    #[serde(default)]
+    pub position: f64,
+    #[serde(default)]
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub played: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SavedProgress {
+    #[serde(rename = "episode", default)]
+    episodes: Vec<Progress>,
+}
+
+/// Progress of every episode that has been played, by episode key.
+#[derive(Debug, Default)]
+pub struct Playback {
+    progress: HashMap<String, Progress>,
+}
+
+impl Playback {
+    pub fn load(config_path: &Path) -> Result<Playback> {
+        let saved = read_records::<SavedProgress>(&config_path.join("playback.xml"))?;
+        Ok(Playback { progress: saved.episodes.into_iter().map(|p| (p.key.clone(), p)).collect() })
+    }
+
+    pub fn save(&self, config_path: &Path) -> Result<()> {
+        let mut progress: Vec<&Progress> = self.progress.values().collect();
+        progress.sort_by(|a, b| a.key.cmp(&b.key));
+        let records: Vec<Record> = progress.iter()
+            .map(|p| {
+                let mut record = vec![
+                    ("key", p.key.clone()),
+                    ("position", format!("{:.1}", p.position)),
+                    ("played", p.played.to_string()),
+                ];
+                if let Some(duration) = p.duration {
+                    record.push(("duration", format!("{:.1}", duration)));
+                }
+                record
+            })
+            .collect();
+        write_records(&config_path.join("playback.xml"), "playback", "episode", &records)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Progress> {
+        self.progress.get(key)
+    }
+
+    /// Records a position reported by the player. Once `threshold` percent of the episode has
+    /// been heard it counts as played; returns true when that just happened.
+    pub fn update(&mut self, key: &str, position: f64, duration: Option<f64>, threshold: u8) -> bool {
+        let progress = self.progress.entry(key.to_string())
+            .or_insert_with(|| Progress { key: key.to_string(), ..Default::default() });
+        progress.position = position;
+        progress.duration = duration.or(progress.duration);
+        let reached = progress.duration.is_some_and(|duration| duration > 0.0 && position >= duration * threshold as f64 / 100.0);
+        let newly_played = reached && !progress.played;
+        progress.played |= reached;
+        newly_played
+    }
+
+    /// Marks an episode played or unplayed by hand. Unplayed episodes start from the beginning.
+    pub fn set_played(&mut self, key: &str, played: bool) {
+        let progress = self.progress.entry(key.to_string())
+            .or_insert_with(|| Progress { key: key.to_string(), ..Default::default() });
+        progress.played = played;
+        if !played {
+            progress.position = 0.0;
+        }
+    }
+
+    /// Copies the stored progress into the episodes of a feed.
+    pub fn apply(&self, episodes: &mut [Episode]) {
+        for ep in episodes {
+            let progress = self.get(&ep.key());
+            ep.position = progress.and_then(|p| Duration::try_from_secs_f64(p.position.max(0.0)).ok());
+            ep.duration = progress.and_then(|p| p.duration).and_then(|d| Duration::try_from_secs_f64(d.max(0.0)).ok());
+            ep.played = progress.is_some_and(|p| p.played);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::{HomilyError, Result};
+use crate::general::{Downloadable, Episode};
+
+/// Whether the player takes over the terminal until it exits or runs in the background.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayerMode {
+    #[default]
+    Terminal,
+    Detached,
+}
+
+/// What to play, filled into the placeholders of the player command.
+#[derive(Clone, Debug)]
+pub struct PlayRequest {
+    /// `Episode::key`, under which the position is saved.
+    pub key: String,
+    /// The downloaded file, or the enclosure URL to stream when there is none.
+    pub file: String,
+    pub url: String,
+    pub title: String,
+    pub start: Duration,
+}
+
+impl PlayRequest {
+    pub fn new(ep: &Episode, start: Duration) -> PlayRequest {
+        let path = ep.save_path();
+        PlayRequest {
+            key: ep.key(),
+            file: if Path::new(&path).exists() { path } else { ep.enclosure.url.clone() },
+            url: ep.enclosure.url.clone(),
+            title: ep.name.clone(),
+            start,
+        }
+    }
+}
+
+/// Splits a command line into words, keeping quoted parts together. There is no shell, so
+/// titles and paths never need escaping.
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            },
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Builds the player command from a template like `mpv --start={start} {file}`. The
+/// placeholders are `{file}`, `{url}`, `{title}` and `{start}` in seconds.
+pub fn player_command(template: &str, request: &PlayRequest) -> Result<Command> {
+    let words: Vec<String> = split_command(template).iter()
+        .map(|word| word
+            .replace("{file}", &request.file)
+            .replace("{url}", &request.url)
+            .replace("{title}", &request.title)
+            .replace("{start}", &request.start.as_secs().to_string()))
+        .collect();
+    let (program, args) = words.split_first()
+        .ok_or_else(|| HomilyError::Config(String::from("the player command is empty")))?;
+    let mut command = Command::new(program);
+    command.args(args);
+    Ok(command)
+}
+
+/// Runs the player in the background, detached from the terminal.
+pub fn spawn_detached(mut command: Command) -> std::io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reap the player when it exits so it doesn't linger as a zombie.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_words_and_quotes() {
+        assert_eq!(split_command("  mpv   --no-video {file} "), ["mpv", "--no-video", "{file}"]);
+        assert_eq!(split_command(r#"vlc --meta-title="{title}" '{file}'"#), ["vlc", "--meta-title={title}", "{file}"]);
+        assert_eq!(split_command(r#"say "" 'it"s'"#), ["say", "", r#"it"s"#]);
+        assert!(split_command("   ").is_empty());
+    }
+
+    #[test]
+    fn placeholders_stay_single_arguments() {
+        let request = PlayRequest {
+            key: String::from("k"),
+            file: String::from("/tmp/My Show/ep 1.mp3"),
+            url: String::from("http://example.com/1.mp3"),
+            title: String::from("Ep 1; rm -rf ~"),
+            start: Duration::from_secs(90),
+        };
+        let command = player_command("player --start={start} --title {title} {file}", &request).unwrap();
+        assert_eq!(command.get_program(), "player");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["--start=90", "--title", "Ep 1; rm -rf ~", "/tmp/My Show/ep 1.mp3"]);
+        assert!(player_command(" ", &request).is_err());
+    }
+}
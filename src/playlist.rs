@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::{HomilyError, Result};
+use crate::general::{Downloadable, Episode, Feed};
+use crate::upnext::QueuedEpisode;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFormat {
+    #[default]
+    M3u8,
+    Pls,
+}
+
+impl PlaylistFormat {
+    pub fn from_name(name: &str) -> Option<PlaylistFormat> {
+        match name.to_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u8),
+            "pls" => Some(PlaylistFormat::Pls),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the file extension, falling back to M3U8.
+    pub fn for_path(path: &Path) -> PlaylistFormat {
+        path.extension()
+            .and_then(|ext| PlaylistFormat::from_name(&ext.to_string_lossy()))
+            .unwrap_or_default()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u8 => "m3u8",
+            PlaylistFormat::Pls => "pls",
+        }
+    }
+}
+
+/// Which episodes go into a playlist.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlaylistSource {
+    /// The downloaded episodes of the named feed, newest first.
+    Feed(String),
+    /// Downloaded episodes not played yet, from every feed, newest first.
+    New,
+    UpNext,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaylistEntry {
+    /// Path relative to the playlist's base folder, or the URL for episodes not downloaded.
+    pub location: String,
+    pub title: String,
+    pub duration: Option<Duration>,
+}
+
+impl PlaylistEntry {
+    pub fn new(ep: &Episode, base: &Path) -> PlaylistEntry {
+        let path = PathBuf::from(ep.save_path());
+        PlaylistEntry {
+            location: if path.exists() {
+                relative_to(&path, base).to_string_lossy().into_owned()
+            } else {
+                ep.enclosure.url.clone()
+            },
+            title: match &ep.feed {
+                Some(feed) => format!("{} - {}", feed.name, ep.name),
+                None => ep.name.clone(),
+            },
+            duration: ep.length(),
+        }
+    }
+
+    fn from_queued(queued: &QueuedEpisode, feeds: &[Feed], base: &Path) -> PlaylistEntry {
+        match queued.episode(feeds) {
+            Some(ep) => PlaylistEntry::new(ep, base),
+            None => PlaylistEntry {
+                location: queued.url.clone(),
+                title: format!("{} - {}", queued.feed, queued.title),
+                duration: None,
+            },
+        }
+    }
+}
+
+/// Collects the episodes of `source`, with file paths relative to `base`.
+pub fn playlist_entries(source: &PlaylistSource, feeds: &[Feed], up_next: &[QueuedEpisode], base: &Path)
+        -> Result<Vec<PlaylistEntry>> {
+    let entries = match source {
+        PlaylistSource::Feed(name) => feeds.iter()
+            .find(|feed| &feed.name == name)
+            .ok_or_else(|| HomilyError::Config(format!("no feed called {}", name)))?
+            .episodes.things.iter()
+            .filter(|ep| ep.downloaded)
+            .map(|ep| PlaylistEntry::new(ep, base))
+            .collect(),
+        PlaylistSource::New => {
+            let mut episodes: Vec<&Episode> = feeds.iter()
+                .flat_map(|feed| feed.episodes.things.iter())
+                .filter(|ep| ep.downloaded && !ep.played)
+                .collect();
+            episodes.sort_by_key(|ep| std::cmp::Reverse(ep.pub_date));
+            episodes.into_iter().map(|ep| PlaylistEntry::new(ep, base)).collect()
+        },
+        PlaylistSource::UpNext => up_next.iter()
+            .map(|queued| PlaylistEntry::from_queued(queued, feeds, base))
+            .collect(),
+    };
+    Ok(entries)
+}
+
+/// Writes `path` relative to `base`, going up with `..` where they part, so the playlist
+/// still works when the folder is copied to another device. Both are made absolute first.
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let absolute = |path: &Path| fs::canonicalize(path)
+        .or_else(|_| std::env::current_dir().map(|dir| dir.join(path)))
+        .unwrap_or_else(|_| path.to_path_buf());
+    let (path, base) = (absolute(path), absolute(base));
+    let path_parts: Vec<Component> = path.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = path_parts.iter().zip(&base_parts).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return path;
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..base_parts.len() {
+        relative.push("..");
+    }
+    for part in &path_parts[common..] {
+        relative.push(part);
+    }
+    relative
+}
+
+pub fn render_playlist(format: PlaylistFormat, entries: &[PlaylistEntry]) -> String {
+    let mut text = String::new();
+    match format {
+        PlaylistFormat::M3u8 => {
+            text.push_str("#EXTM3U\n");
+            for entry in entries {
+                let secs = entry.duration.map(|d| d.as_secs() as i64).unwrap_or(-1);
+                text.push_str(&format!("#EXTINF:{},{}\n{}\n", secs, entry.title.replace('\n', " "), entry.location));
+            }
+        },
+        PlaylistFormat::Pls => {
+            text.push_str("[playlist]\n");
+            for (i, entry) in entries.iter().enumerate() {
+                let n = i + 1;
+                let secs = entry.duration.map(|d| d.as_secs() as i64).unwrap_or(-1);
+                text.push_str(&format!("File{}={}\nTitle{}={}\nLength{}={}\n",
+                    n, entry.location, n, entry.title.replace('\n', " "), n, secs));
+            }
+            text.push_str(&format!("NumberOfEntries={}\nVersion=2\n", entries.len()));
+        },
+    }
+    text
+}
+
+pub fn write_playlist(path: &Path, format: PlaylistFormat, entries: &[PlaylistEntry]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| HomilyError::io(parent, e))?;
+    }
+    fs::write(path, render_playlist(format, entries)).map_err(|e| HomilyError::io(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_relative_to_the_playlist_folder() {
+        let relative = |path: &str, base: &str| relative_to(Path::new(path), Path::new(base));
+        assert_eq!(relative("/music/show/ep.mp3", "/music/playlists"), Path::new("../show/ep.mp3"));
+        assert_eq!(relative("/music/show/ep.mp3", "/music"), Path::new("show/ep.mp3"));
+        assert_eq!(relative("/music/show/ep.mp3", "/other/deep/folder"), Path::new("../../../music/show/ep.mp3"));
+    }
+
+    #[test]
+    fn renders_m3u8_and_pls() {
+        let entries = [
+            PlaylistEntry { location: String::from("show/1.mp3"), title: String::from("Show - One\nTwo"),
+                duration: Some(Duration::from_secs(61)) },
+            PlaylistEntry { location: String::from("http://example.com/2.mp3"), title: String::from("Show - Three"),
+                duration: None },
+        ];
+        assert_eq!(render_playlist(PlaylistFormat::M3u8, &entries), "#EXTM3U\n\
+            #EXTINF:61,Show - One Two\nshow/1.mp3\n\
+            #EXTINF:-1,Show - Three\nhttp://example.com/2.mp3\n");
+        assert_eq!(render_playlist(PlaylistFormat::Pls, &entries), "[playlist]\n\
+            File1=show/1.mp3\nTitle1=Show - One Two\nLength1=61\n\
+            File2=http://example.com/2.mp3\nTitle2=Show - Three\nLength2=-1\n\
+            NumberOfEntries=2\nVersion=2\n");
+        assert_eq!(PlaylistFormat::for_path(Path::new("new.PLS")), PlaylistFormat::Pls);
+        assert_eq!(PlaylistFormat::for_path(Path::new("new")), PlaylistFormat::M3u8);
+    }
+}
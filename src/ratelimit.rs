@@ -0,0 +1,163 @@
+use std::cmp::max;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use serde::{Deserialize, Deserializer};
+
+/// A time-of-day window with its own global rate limit, e.g. unlimited at night.
+/// Windows where `from` is later than `to` wrap around midnight.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RateWindow {
+    #[serde(deserialize_with = "parse_time")]
+    pub from: NaiveTime,
+    #[serde(deserialize_with = "parse_time")]
+    pub to: NaiveTime,
+    #[serde(deserialize_with = "parse_rate", default)]
+    pub limit: Option<u64>,
+}
+
+impl RateWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            self.from <= time || time < self.to
+        }
+    }
+}
+
+struct Bucket {
+    limit: Option<u64>,
+    use_schedule: bool,
+    next_free: Instant,
+}
+
+/// Spaces out chunks so that the bytes passing through stay under a limit in bytes per second.
+/// One limiter is shared by all downloads for the global limit, and each download can have
+/// its own for the per-download limit.
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+    schedule: Vec<RateWindow>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: Option<u64>, schedule: Vec<RateWindow>) -> RateLimiter {
+        RateLimiter {
+            bucket: Mutex::new(Bucket { limit, use_schedule: true, next_free: Instant::now() }),
+            schedule,
+        }
+    }
+
+    /// The limit in effect right now, taking the schedule into account.
+    pub fn current_limit(&self) -> Option<u64> {
+        let bucket = self.bucket.lock().unwrap();
+        self.effective_limit(&bucket)
+    }
+
+    /// Sets the limit by hand. This overrides the schedule for the rest of the session.
+    pub fn set_limit(&self, limit: Option<u64>) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.limit = limit;
+        bucket.use_schedule = false;
+    }
+
+    /// Books `bytes` against the limit and returns how long the caller should wait
+    /// before reading more.
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        match self.effective_limit(&bucket) {
+            Some(limit) if limit > 0 => {
+                let start = max(now, bucket.next_free);
+                bucket.next_free = start + Duration::from_secs_f64(bytes as f64 / limit as f64);
+                bucket.next_free - now
+            },
+            _ => {
+                bucket.next_free = now;
+                Duration::from_secs(0)
+            },
+        }
+    }
+
+    fn effective_limit(&self, bucket: &Bucket) -> Option<u64> {
+        if bucket.use_schedule {
+            let now = Local::now().time();
+            if let Some(window) = self.schedule.iter().find(|w| w.contains(now)) {
+                return window.limit;
+            }
+        }
+        bucket.limit
+    }
+}
+
+pub fn rate_pretty(limit: Option<u64>) -> String {
+    match limit {
+        Some(bytes) if bytes > 0 => format!("{}/s", crate::general::bytes_pretty(bytes)),
+        _ => String::from("unlimited"),
+    }
+}
+
+/// Parses a rate such as `500K`, `2M` or `64000` (bytes per second). `0` means unlimited.
+pub fn parse_rate<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+        where D: Deserializer<'de>, {
+    let s = String::deserialize(deserializer)?;
+    let s = s.trim();
+    let (digits, multiplier) = match s.chars().last() {
+        Some('K') | Some('k') => (&s[..s.len() - 1], 1000),
+        Some('M') | Some('m') => (&s[..s.len() - 1], 1000 * 1000),
+        _ => (s, 1),
+    };
+    match digits.trim().parse::<u64>().ok().and_then(|n| n.checked_mul(multiplier)) {
+        Some(0) => Ok(None),
+        Some(n) => Ok(Some(n)),
+        None => Err(serde::de::Error::custom(format!("invalid rate: {}", s))),
+    }
+}
+
+fn parse_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+        where D: Deserializer<'de>, {
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error, StrDeserializer};
+    use serde::de::IntoDeserializer;
+
+    fn rate(text: &str) -> Result<Option<u64>, Error> {
+        parse_rate::<StrDeserializer<Error>>(text.into_deserializer())
+    }
+
+    fn time(text: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(text, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn parses_rates() {
+        assert_eq!(rate("64000").unwrap(), Some(64000));
+        assert_eq!(rate(" 500K ").unwrap(), Some(500_000));
+        assert_eq!(rate("2m").unwrap(), Some(2_000_000));
+        assert_eq!(rate("0").unwrap(), None);
+        assert!(rate("fast").is_err());
+        assert!(rate("").is_err());
+        assert!(rate("99999999999999999M").is_err());
+    }
+
+    #[test]
+    fn windows_wrap_around_midnight() {
+        let day = RateWindow { from: time("08:00"), to: time("18:00"), limit: None };
+        assert!(day.contains(time("08:00")));
+        assert!(day.contains(time("12:30")));
+        assert!(!day.contains(time("18:00")));
+        assert!(!day.contains(time("03:00")));
+        let night = RateWindow { from: time("23:00"), to: time("06:00"), limit: None };
+        assert!(night.contains(time("23:30")));
+        assert!(night.contains(time("00:00")));
+        assert!(night.contains(time("05:59")));
+        assert!(!night.contains(time("06:00")));
+        assert!(!night.contains(time("12:00")));
+    }
+}
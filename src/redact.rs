@@ -0,0 +1,135 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::secrets::Secrets;
+
+struct Redactor {
+    secrets: Arc<Secrets>,
+    reveal: bool,
+}
+
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// Words of query parameter and header names that mark their values as secret.
+const SENSITIVE_NAMES: [&str; 16] = ["token", "key", "apikey", "auth", "authorization", "pass", "password", "passwd",
+    "secret", "sig", "signature", "session", "sessionid", "cookie", "credential", "credentials"];
+
+/// Sets the credentials to mask and whether the `reveal-secrets` setting turns masking off.
+/// Until this is called only the patterns in URLs are masked.
+pub fn init(secrets: Arc<Secrets>, reveal: bool) {
+    let _ = REDACTOR.set(Redactor { secrets, reveal });
+}
+
+/// Masks credentials in URLs, token-like query parameters and known passwords and tokens.
+/// Everything homily shows or logs goes through here.
+pub fn redact(text: &str) -> String {
+    match REDACTOR.get() {
+        Some(redactor) if redactor.reveal => text.to_string(),
+        Some(redactor) => redact_urls(&redactor.secrets.redact(text)),
+        None => redact_urls(text),
+    }
+}
+
+/// Masks the whole value of cookie, authorization and token headers.
+pub fn redact_header(name: &str, value: &str) -> String {
+    let reveal = REDACTOR.get().is_some_and(|redactor| redactor.reveal);
+    if !reveal && is_sensitive(name) {
+        String::from("***")
+    } else {
+        redact(value)
+    }
+}
+
+/// Checks each word of `name`, split at punctuation and camelCase humps, so `access_token`,
+/// `X-Api-Key` and `apiKey` are masked but `keyword` or `passage` are not.
+fn is_sensitive(name: &str) -> bool {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        let hump = c.is_uppercase() && previous_lower;
+        if (hump || !c.is_alphanumeric()) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        }
+        previous_lower = c.is_lowercase() || c.is_numeric();
+    }
+    words.push(word);
+    words.iter().any(|word| SENSITIVE_NAMES.contains(&word.as_str()))
+}
+
+fn redact_urls(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("://") {
+        let start = start + 3;
+        let end = rest[start..]
+            .find(|c: char| c.is_whitespace() || "\"'<>()[]{}".contains(c))
+            .map_or(rest.len(), |end| start + end);
+        redacted.push_str(&rest[..start]);
+        redacted.push_str(&redact_url(&rest[start..end]));
+        rest = &rest[end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Masks the user info and sensitive query values of a URL given without its `scheme://`.
+fn redact_url(url: &str) -> String {
+    let authority_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+    let mut redacted = match url[..authority_end].rfind('@') {
+        Some(at) => format!("***@{}", &url[at + 1..authority_end]),
+        None => url[..authority_end].to_string(),
+    };
+    let rest = &url[authority_end..];
+    let query_start = match rest.find('?') {
+        Some(query_start) => query_start + 1,
+        None => {
+            redacted.push_str(rest);
+            return redacted;
+        },
+    };
+    let query_end = rest[query_start..].find('#').map_or(rest.len(), |end| query_start + end);
+    let params: Vec<String> = rest[query_start..query_end].split('&').map(|param| match param.split_once('=') {
+        Some((name, _)) if is_sensitive(name) => format!("{}=***", name),
+        _ => param.to_string(),
+    }).collect();
+    redacted.push_str(&rest[..query_start]);
+    redacted.push_str(&params.join("&"));
+    redacted.push_str(&rest[query_end..]);
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_words_of_names() {
+        for name in ["token", "access_token", "X-Api-Key", "apiKey", "apikey", "Authorization", "Set-Cookie",
+                "X-Amz-Signature", "session_id", "PASSWORD"] {
+            assert!(is_sensitive(name), "{} should be masked", name);
+        }
+        for name in ["keyword", "passage", "monkey", "author", "sigma", "tokenizer", "format", "Accept"] {
+            assert!(!is_sensitive(name), "{} should be shown", name);
+        }
+    }
+
+    #[test]
+    fn masks_user_info_and_sensitive_parameters() {
+        assert_eq!(redact_url("user:pw@example.com/feed.rss"), "***@example.com/feed.rss");
+        assert_eq!(redact_url("example.com/ep.mp3?keyword=podcast&token=abc&page=2#t=10"),
+            "example.com/ep.mp3?keyword=podcast&token=***&page=2#t=10");
+        assert_eq!(redact_url("example.com/a@b?x=1"), "example.com/a@b?x=1");
+        assert_eq!(redact_urls("failed: https://u:p@example.com/x?sig=1 (403)"),
+            "failed: https://***@example.com/x?sig=*** (403)");
+    }
+
+    #[test]
+    fn masks_whole_sensitive_headers() {
+        assert_eq!(redact_header("Cookie", "id=1"), "***");
+        assert_eq!(redact_header("X-Auth-Token", "abc"), "***");
+        assert_eq!(redact_header("Location", "https://example.com/?api_key=abc"), "https://example.com/?api_key=***");
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs;
+use std::path::Path;
+
+use log::info;
+use quick_xml::de::from_str;
+use reqwest::{Client, Method, RequestBuilder, Url};
+use serde::Deserialize;
+
+use crate::error::{HomilyError, Result};
+use crate::general::Feed;
+
+/// Credentials for one feed from `secrets.xml`, e.g.
+/// `<credential feed="Premium" username="me" password="pw"/>`, `token="..."` for a bearer
+/// token, or `url-token="key=..."` for a token passed in the query string.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Credential {
+    #[serde(default)]
+    pub feed: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+    #[serde(rename = "url-token")]
+    pub url_token: Option<String>,
+    /// Host of the feed's URL. Enclosures are only sent the credential when they are on it too.
+    #[serde(skip)]
+    pub host: String,
+}
+
+impl Credential {
+    fn secret_values(&self) -> Vec<&str> {
+        let url_token = self.url_token.as_ref().map(|t| t.split_once('=').map_or(t.as_str(), |(_, value)| value));
+        vec![self.password.as_deref(), self.token.as_deref(), url_token]
+            .into_iter().flatten().filter(|value| !value.is_empty()).collect()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SecretsFile {
+    #[serde(rename = "credential", default)]
+    credentials: Vec<Credential>,
+}
+
+/// Credentials from `secrets.xml` in the homily folder and from `~/.netrc`.
+#[derive(Debug, Default)]
+pub struct Secrets {
+    feeds: Vec<Credential>,
+    netrc: Vec<Credential>,
+}
+
+impl Secrets {
+    /// Loads both files if they exist. `secrets.xml` must not be accessible to other users;
+    /// a `.netrc` that is, is skipped with a warning in the log.
+    pub fn load(config_path: &Path, feeds: &[Feed]) -> Result<Secrets> {
+        let mut secrets = Secrets::default();
+        let path = config_path.join("secrets.xml");
+        if path.exists() {
+            check_permissions(&path)?;
+            let text = fs::read_to_string(&path).map_err(|e| HomilyError::io(&path, e))?;
+            secrets.feeds = from_str::<SecretsFile>(&text).map_err(|e| HomilyError::parse(&path, e))?.credentials;
+            for credential in secrets.feeds.iter_mut() {
+                match feeds.iter().find(|feed| feed.name == credential.feed) {
+                    Some(feed) => credential.host = host(&feed.url),
+                    None => info!("secrets.xml: no feed named {}", credential.feed),
+                }
+            }
+        }
+        if let Some(netrc) = dirs::home_dir().map(|home| home.join(".netrc")).filter(|path| path.exists()) {
+            match check_permissions(&netrc).and_then(|_| fs::read_to_string(&netrc).map_err(|e| HomilyError::io(&netrc, e))) {
+                Ok(text) => secrets.netrc = parse_netrc(&text),
+                Err(e) => info!("Ignoring .netrc: {}", e),
+            }
+        }
+        Ok(secrets)
+    }
+
+    /// The credential for a request to `url` made for `feed`: the feed's own if `url` is on
+    /// the feed's host, otherwise a `.netrc` entry for the host.
+    pub fn credential(&self, feed: Option<&str>, url: &str) -> Option<&Credential> {
+        let url_host = host(url);
+        self.feeds.iter()
+            .find(|c| Some(c.feed.as_str()) == feed && c.host == url_host)
+            .or_else(|| self.netrc.iter().find(|c| c.host == url_host))
+    }
+
+    /// Starts a request with any credential for it applied.
+    pub fn request(&self, client: &Client, method: Method, url: &str, feed: Option<&str>) -> RequestBuilder {
+        let credential = match self.credential(feed, url) {
+            Some(credential) => credential,
+            None => return client.request(method, url),
+        };
+        let mut url = url.to_string();
+        if let Some(token) = &credential.url_token {
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(token);
+        }
+        let mut request = client.request(method, &url);
+        if let Some(username) = &credential.username {
+            request = request.basic_auth(username, credential.password.as_ref());
+        }
+        if let Some(token) = &credential.token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    /// Masks every known password and token in `text`.
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for value in self.feeds.iter().chain(self.netrc.iter()).flat_map(|c| c.secret_values()) {
+            text = text.replace(value, "***");
+        }
+        text
+    }
+}
+
+fn host(url: &str) -> String {
+    Url::parse(url).ok().and_then(|url| url.host_str().map(String::from)).unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path).map_err(|e| HomilyError::io(path, e))?.permissions().mode();
+    if mode & 0o077 != 0 {
+        return Err(HomilyError::Config(format!(
+            "{} can be read by other users, run chmod 600 on it", path.to_string_lossy())));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Reads `machine`, `login` and `password` entries; `default` and macros are ignored.
+fn parse_netrc(text: &str) -> Vec<Credential> {
+    let mut credentials: Vec<Credential> = vec![];
+    // False inside a `default` entry, whose login must not end up on the machine before it.
+    let mut in_machine = false;
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => {
+                credentials.push(Credential {
+                    host: tokens.next().unwrap_or_default().to_string(),
+                    ..Default::default()
+                });
+                in_machine = true;
+            },
+            "default" => in_machine = false,
+            "login" | "password" => {
+                let value = tokens.next().map(String::from);
+                if let Some(credential) = credentials.last_mut().filter(|_| in_machine) {
+                    if token == "login" {
+                        credential.username = value;
+                    } else {
+                        credential.password = value;
+                    }
+                }
+            },
+            "macdef" => break,
+            _ => {},
+        }
+    }
+    credentials
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_machines_and_skips_default() {
+        let credentials = parse_netrc("machine feeds.example.com login me password pw\n\
+            machine cdn.example.com\n  login other\n\
+            default login anonymous password guest\n\
+            macdef init\nmachine macro.example.com login no\n");
+        assert_eq!(credentials.len(), 2);
+        assert_eq!(credentials[0].host, "feeds.example.com");
+        assert_eq!(credentials[0].username.as_deref(), Some("me"));
+        assert_eq!(credentials[0].password.as_deref(), Some("pw"));
+        assert_eq!(credentials[1].host, "cdn.example.com");
+        assert_eq!(credentials[1].username.as_deref(), Some("other"));
+        assert_eq!(credentials[1].password, None);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use quick_xml::de::from_str;
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use quick_xml::Writer;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::error::{HomilyError, Result};
+use crate::general::{Download, DownloadState, Message};
+
+/// Attributes of one element in a file written by `write_records`.
+pub type Record<'a> = Vec<(&'a str, String)>;
+
+/// Writes `<root>` holding one empty `<item .../>` element per record. The file is replaced
+/// in one step so a crash never leaves it half written.
+pub fn write_records(path: &Path, root: &str, item: &str, records: &[Record]) -> Result<()> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    let xml_error = |e: quick_xml::Error| HomilyError::Config(format!("couldn't write {}: {}", path.to_string_lossy(), e));
+    writer.write_event(Event::Start(BytesStart::borrowed_name(root.as_bytes()))).map_err(xml_error)?;
+    for record in records {
+        let mut element = BytesStart::borrowed_name(item.as_bytes());
+        for (name, value) in record {
+            element.push_attribute((*name, value.as_str()));
+        }
+        writer.write_event(Event::Empty(element)).map_err(xml_error)?;
+    }
+    writer.write_event(Event::End(BytesEnd::borrowed(root.as_bytes()))).map_err(xml_error)?;
+
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    fs::write(&temp, writer.into_inner()).map_err(|e| HomilyError::io(&temp, e))?;
+    fs::rename(&temp, path).map_err(|e| HomilyError::io(path, e))
+}
+
+/// Reads a file written by `write_records`. A file that doesn't exist yet reads as empty.
+pub fn read_records<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match fs::read_to_string(path) {
+        Ok(text) => from_str(&text).map_err(|e| HomilyError::parse(path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(HomilyError::io(path, e)),
+    }
+}
+
+/// What a saved download was for, which decides what happens once it finishes.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum SavedKind {
+    Feed,
+    Episode,
+    /// A transcript or chapters file that goes with an episode.
+    Extra,
+}
+
+/// An unfinished download as stored in `queue.xml`.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SavedDownload {
+    pub url: String,
+    pub path: PathBuf,
+    pub state: DownloadState,
+    #[serde(default)]
+    pub feed: Option<String>,
+    /// Title of the episode, absent for feed refreshes.
+    #[serde(default)]
+    pub episode: Option<String>,
+    #[serde(default)]
+    pub bytes: u64,
+    #[serde(default)]
+    pub total: u64,
+    /// Missing in files from older versions, where it follows from `feed` and `episode`.
+    #[serde(default)]
+    pub kind: Option<SavedKind>,
+    #[serde(rename = "type", default)]
+    pub expected_type: Option<String>,
+}
+
+impl SavedDownload {
+    pub fn kind(&self) -> SavedKind {
+        match (self.kind, &self.episode) {
+            (Some(kind), _) => kind,
+            (None, Some(_)) => SavedKind::Episode,
+            (None, None) => SavedKind::Feed,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SavedQueue {
+    #[serde(rename = "download", default)]
+    downloads: Vec<SavedDownload>,
+}
+
+/// Saves every download that hasn't finished or been cancelled to `queue.xml`.
+pub fn save_queue(config_path: &Path, downloads: &[Download]) -> Result<()> {
+    let records: Vec<Record> = downloads.iter()
+        // Artwork is fetched again on the next feed update rather than restored.
+        .filter(|dl| dl.state != DownloadState::Done && dl.state != DownloadState::Cancelled && dl.artwork.is_none())
+        .map(|dl| {
+            let mut record = vec![
+                ("url", dl.url.clone()),
+                ("path", dl.path.to_string_lossy().into_owned()),
+                ("state", format!("{:?}", dl.state)),
+                ("bytes", dl.downloaded_bytes.to_string()),
+                ("total", dl.total_bytes.to_string()),
+            ];
+            if let Some(feed) = &dl.feed_name {
+                record.push(("feed", feed.clone()));
+            }
+            let kind = match &dl.success_message {
+                Some(Message::EpisodeDownloaded(episode)) => {
+                    record.push(("episode", episode.clone()));
+                    SavedKind::Episode
+                },
+                Some(Message::FeedDownloaded(_)) => SavedKind::Feed,
+                _ => SavedKind::Extra,
+            };
+            record.push(("kind", format!("{:?}", kind)));
+            if let Some(expected_type) = &dl.expected_type {
+                record.push(("type", expected_type.clone()));
+            }
+            record
+        })
+        .collect();
+    write_records(&config_path.join("queue.xml"), "queue", "download", &records)
+}
+
+pub fn load_queue(config_path: &Path) -> Result<Vec<SavedDownload>> {
+    Ok(read_records::<SavedQueue>(&config_path.join("queue.xml"))?.downloads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_keeps_what_each_download_is_for() {
+        let dir = std::env::temp_dir().join(format!("homily-queue-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dl = |path: &str, success_message| Download {
+            path: PathBuf::from(path),
+            feed_name: Some(String::from("Show")),
+            success_message,
+            state: DownloadState::Paused,
+            ..Default::default()
+        };
+        let transcript = Download { expected_type: Some(String::from("text/html")), ..dl("ep.html", None) };
+        save_queue(&dir, &[
+            dl("show.rss", Some(Message::FeedDownloaded(String::from("Show")))),
+            dl("ep.mp3", Some(Message::EpisodeDownloaded(String::from("Ep. 1")))),
+            transcript,
+        ]).unwrap();
+        let saved = load_queue(&dir).unwrap();
+        let kinds: Vec<SavedKind> = saved.iter().map(SavedDownload::kind).collect();
+        assert_eq!(kinds, [SavedKind::Feed, SavedKind::Episode, SavedKind::Extra]);
+        assert_eq!(saved[1].episode.as_deref(), Some("Ep. 1"));
+        assert_eq!(saved[2].expected_type.as_deref(), Some("text/html"));
+
+        fs::write(dir.join("queue.xml"), r#"<queue><download url="u" path="ep.mp3" state="Paused" feed="Show" episode="One"/>
+            <download url="u" path="show.rss" state="Queued" feed="Show"/></queue>"#).unwrap();
+        let kinds: Vec<SavedKind> = load_queue(&dir).unwrap().iter().map(SavedDownload::kind).collect();
+        assert_eq!(kinds, [SavedKind::Episode, SavedKind::Feed]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use crate::general::Message;
+use crate::redact::redact;
 
 use futures_channel::mpsc::UnboundedSender;
 use log::{Record, Level, Metadata};
@@ -16,8 +17,11 @@ impl log::Log for StringLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            let utx = self.utx.lock().unwrap();
-            utx.unbounded_send(Message::LogMessage(format!("{} -{}", record.level(), record.args()))).unwrap();
+            // A logger has nowhere to report its own failures, so a poisoned lock or a
+            // closed channel just drops the message.
+            if let Ok(utx) = self.utx.lock() {
+                let _ = utx.unbounded_send(Message::LogMessage(redact(&format!("{} -{}", record.level(), record.args()))));
+            }
         }
     }
 
@@ -0,0 +1,158 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use id3::frame::{Comment, Picture, PictureType};
+use id3::{TagLike, Version};
+use mp4ameta::Img;
+
+use crate::artwork::find_artwork;
+use crate::error::{HomilyError, Result};
+use crate::general::{Episode, Feed};
+
+/// What gets written into a downloaded episode's tags.
+#[derive(Clone, Debug)]
+pub struct TagInfo {
+    pub album: String,
+    pub title: String,
+    pub date: Option<DateTime<FixedOffset>>,
+    pub episode: Option<u32>,
+    pub season: Option<u32>,
+    pub comment: Option<String>,
+    pub cover: Option<PathBuf>,
+}
+
+impl TagInfo {
+    pub fn new(feed: &Feed, ep: &Episode) -> TagInfo {
+        TagInfo {
+            album: feed.name.clone(),
+            title: ep.name.clone(),
+            date: ep.pub_date,
+            episode: ep.episode_number,
+            season: ep.season,
+            comment: ep.description.as_deref().map(strip_html).filter(|text| !text.is_empty()),
+            cover: find_artwork(&Path::new(&feed.save_folder).join("cover")),
+        }
+    }
+}
+
+/// Descriptions are usually HTML; players show tags as plain text.
+pub(crate) fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {},
+        }
+    }
+    text.replace("&nbsp;", " ").replace("&lt;", "<").replace("&gt;", ">")
+        .replace("&quot;", "\"").replace("&#39;", "'").replace("&amp;", "&")
+        .trim().to_string()
+}
+
+enum Format {
+    Mp3,
+    Mp4,
+}
+
+/// Looks at the first bytes rather than the name, since episodes are always saved as `.mp3`.
+fn sniff(path: &Path) -> Result<Option<Format>> {
+    let mut head = [0u8; 12];
+    let mut file = File::open(path).map_err(|e| HomilyError::io(path, e))?;
+    let read = file.read(&mut head).map_err(|e| HomilyError::io(path, e))?;
+    Ok(match &head[..read] {
+        [b'I', b'D', b'3', ..] => Some(Format::Mp3),
+        [0xff, second, ..] if second & 0xe0 == 0xe0 => Some(Format::Mp3),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(Format::Mp4),
+        _ => None,
+    })
+}
+
+/// Rewrites the tags of an MP3 (as ID3v2.4) or M4A file. Only the metadata is replaced;
+/// other file types are left alone.
+pub fn tag_file(path: &Path, info: &TagInfo) -> Result<()> {
+    let cover = match &info.cover {
+        Some(cover) => Some((fs::read(cover).map_err(|e| HomilyError::io(cover, e))?,
+            cover.extension().map(|ext| ext.to_string_lossy().into_owned()).unwrap_or_default())),
+        None => None,
+    };
+    match sniff(path)? {
+        Some(Format::Mp3) => tag_mp3(path, info, cover),
+        Some(Format::Mp4) => tag_mp4(path, info, cover),
+        None => Err(HomilyError::tag(path, "not an MP3 or M4A file")),
+    }
+}
+
+fn tag_mp3(path: &Path, info: &TagInfo, cover: Option<(Vec<u8>, String)>) -> Result<()> {
+    let mut tag = id3::no_tag_ok(id3::Tag::read_from_path(path))
+        .map_err(|e| HomilyError::tag(path, e))?
+        .unwrap_or_default();
+    tag.set_album(info.album.as_str());
+    tag.set_artist(info.album.as_str());
+    tag.set_album_artist(info.album.as_str());
+    tag.set_title(info.title.as_str());
+    tag.set_genre("Podcast");
+    if let Some(date) = info.date {
+        tag.set_date_released(id3::Timestamp {
+            year: date.year(),
+            month: Some(date.month() as u8),
+            day: Some(date.day() as u8),
+            hour: None,
+            minute: None,
+            second: None,
+        });
+    }
+    if let Some(episode) = info.episode {
+        tag.set_track(episode);
+    }
+    // ID3 has no season frame; the disc number is what most players show instead.
+    if let Some(season) = info.season {
+        tag.set_disc(season);
+    }
+    if let Some(comment) = &info.comment {
+        tag.remove_comment(Some(""), None);
+        tag.add_frame(Comment { lang: String::from("eng"), description: String::new(), text: comment.clone() });
+    }
+    if let Some((data, ext)) = cover {
+        tag.remove_picture_by_type(PictureType::CoverFront);
+        tag.add_frame(Picture {
+            mime_type: format!("image/{}", if ext == "jpg" { "jpeg" } else { &ext }),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data,
+        });
+    }
+    tag.write_to_path(path, Version::Id3v24).map_err(|e| HomilyError::tag(path, e))
+}
+
+fn tag_mp4(path: &Path, info: &TagInfo, cover: Option<(Vec<u8>, String)>) -> Result<()> {
+    let mut tag = mp4ameta::Tag::read_from_path(path).map_err(|e| HomilyError::tag(path, e))?;
+    tag.set_album(info.album.as_str());
+    tag.set_artist(info.album.as_str());
+    tag.set_album_artist(info.album.as_str());
+    tag.set_title(info.title.as_str());
+    tag.set_genre("Podcast");
+    if let Some(date) = info.date {
+        tag.set_year(date.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ").to_string());
+    }
+    if let Some(episode) = info.episode {
+        tag.set_tv_episode(episode);
+    }
+    if let Some(season) = info.season {
+        tag.set_tv_season(season);
+    }
+    if let Some(comment) = &info.comment {
+        tag.set_comment(comment.as_str());
+    }
+    // MP4 only holds JPEG, PNG and BMP artwork.
+    match cover {
+        Some((data, ext)) if ext == "png" => tag.set_artwork(Img::png(data)),
+        Some((data, ext)) if ext == "jpg" => tag.set_artwork(Img::jpeg(data)),
+        _ => {},
+    }
+    tag.write_to_path(path).map_err(|e| HomilyError::tag(path, e))
+}
@@ -0,0 +1,203 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tuikit::attr::{Attr, Effect};
+
+use crate::error::{HomilyError, Result};
+use crate::general::Styled;
+use crate::tagging::strip_html;
+
+/// One cue of a transcript. Plain text and HTML transcripts have no timestamps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub start: Option<Duration>,
+    pub text: String,
+    /// Set while the cue matches the current search.
+    pub matched: bool,
+}
+
+impl Display for Cue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.start {
+            Some(start) => write!(f, "{:>8} {}", format_time(start), self.text),
+            None => write!(f, "{}", self.text),
+        }
+    }
+}
+
+impl Styled for Cue {
+    fn styles(&self) -> Attr {
+        if self.matched {
+            Attr::from(Effect::BOLD)
+        } else {
+            Attr::from(Effect::empty())
+        }
+    }
+}
+
+/// Formats a position as `34:12` or `1:02:00`.
+pub fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Parses `01:02:03.500`, `02:03.500` or the SRT form `01:02:03,500`. Negative, infinite
+/// or absurdly large times come from broken files and read as `None`.
+pub fn parse_time(text: &str) -> Option<Duration> {
+    let text = text.trim().replace(',', ".");
+    let mut secs = 0.0;
+    for part in text.split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Reads WebVTT and SRT. Both are blank-line separated blocks where the line holding `-->`
+/// starts a cue and the lines after it are its text.
+pub fn parse_cues(text: &str) -> Vec<Cue> {
+    let mut cues = vec![];
+    for block in text.replace("\r\n", "\n").split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let start = match lines.next().and_then(|line| line.split("-->").next()).and_then(parse_time) {
+            Some(start) => start,
+            None => continue,
+        };
+        let text = lines.map(strip_html).filter(|line| !line.is_empty()).collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            cues.push(Cue { start: Some(start), text, matched: false });
+        }
+    }
+    cues
+}
+
+#[derive(Deserialize)]
+struct JsonTranscript {
+    segments: Vec<JsonSegment>,
+}
+
+#[derive(Deserialize)]
+struct JsonSegment {
+    #[serde(rename = "startTime", default)]
+    start_time: Option<f64>,
+    #[serde(default)]
+    speaker: Option<String>,
+    body: String,
+}
+
+/// Reads the Podcasting 2.0 JSON transcript format.
+pub fn parse_json(text: &str) -> std::result::Result<Vec<Cue>, serde_json::Error> {
+    let transcript: JsonTranscript = serde_json::from_str(text)?;
+    Ok(transcript.segments.into_iter()
+        .map(|segment| Cue {
+            start: segment.start_time.and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+            text: match segment.speaker {
+                Some(speaker) => format!("{}: {}", speaker, segment.body.trim()),
+                None => segment.body.trim().to_string(),
+            },
+            matched: false,
+        })
+        .collect())
+}
+
+/// Loads a downloaded transcript, choosing the parser by the file's extension.
+pub fn load_transcript(path: &Path) -> Result<Vec<Cue>> {
+    let text = fs::read_to_string(path).map_err(|e| HomilyError::io(path, e))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("vtt") | Some("srt") => Ok(parse_cues(&text)),
+        Some("json") => parse_json(&text).map_err(|e| HomilyError::json(path, e)),
+        _ => Ok(text.split("</p>")
+            .flat_map(|paragraph| strip_html(paragraph).lines().map(String::from).collect::<Vec<_>>())
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .map(|text| Cue { start: None, text, matched: false })
+            .collect()),
+    }
+}
+
+/// The state of an incremental search in the transcript view.
+#[derive(Debug, Default)]
+pub struct Search {
+    pub query: String,
+    /// True while the query is being typed.
+    pub editing: bool,
+    /// Where the cursor was when the search started, so typing searches forward from there.
+    pub origin: usize,
+}
+
+impl Search {
+    /// Marks the cues containing the query, ignoring case, and returns how many there are.
+    pub fn mark(&self, cues: &mut [Cue]) -> usize {
+        let query = self.query.to_lowercase();
+        cues.iter_mut()
+            .map(|cue| {
+                cue.matched = !query.is_empty() && cue.text.to_lowercase().contains(&query);
+                cue.matched
+            })
+            .filter(|matched| *matched)
+            .count()
+    }
+
+    /// The closest match from `from` onwards, or backwards, wrapping around the end.
+    pub fn find(cues: &[Cue], from: usize, forward: bool) -> Option<usize> {
+        let len = cues.len();
+        (0..len)
+            .map(|step| if forward { (from + step) % len } else { (from + len - step) % len })
+            .find(|&index| cues[index].matched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vtt_and_srt_times() {
+        assert_eq!(parse_time("01:02:03.500"), Some(Duration::from_millis(3_723_500)));
+        assert_eq!(parse_time("02:03.500"), Some(Duration::from_millis(123_500)));
+        assert_eq!(parse_time("01:02:03,500"), Some(Duration::from_millis(3_723_500)));
+        assert_eq!(parse_time(" 1800 "), Some(Duration::from_secs(1800)));
+    }
+
+    #[test]
+    fn rejects_broken_times() {
+        for text in ["-5", "NaN", "inf", "1e300", "", "1:xx", "00:-01"] {
+            assert_eq!(parse_time(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_vtt_cues() {
+        let cues = parse_cues("WEBVTT\n\n1\n00:00:01.000 --> 00:00:02.000\n<v Ann>Hello</v>\nthere\n\n\
+            00:01:00.000 --> 00:01:05.000 align:start\nSecond\n\nNOTE no cue here\n");
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].start, Some(Duration::from_secs(1)));
+        assert_eq!(cues[0].text, "Hello there");
+        assert_eq!(cues[1].start, Some(Duration::from_secs(60)));
+        assert_eq!(cues[1].text, "Second");
+    }
+
+    #[test]
+    fn parses_srt_cues_with_crlf() {
+        let cues = parse_cues("1\r\n00:00:01,500 --> 00:00:02,000\r\nOne\r\n\r\n2\r\nbroken --> 00:00:03,000\r\nSkipped\r\n");
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].start, Some(Duration::from_millis(1500)));
+        assert_eq!(cues[0].text, "One");
+    }
+
+    #[test]
+    fn json_segments_with_bad_times_have_no_start() {
+        let cues = parse_json(r#"{"segments": [{"startTime": 1e300, "body": "Far"}, {"startTime": -1, "body": "Before"},
+            {"startTime": 2.5, "speaker": "Ann", "body": " Hi "}]}"#).unwrap();
+        assert_eq!(cues[0].start, None);
+        assert_eq!(cues[1].start, None);
+        assert_eq!(cues[2].start, Some(Duration::from_millis(2500)));
+        assert_eq!(cues[2].text, "Ann: Hi");
+    }
+}
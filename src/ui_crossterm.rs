@@ -21,13 +21,26 @@ impl TermAdapter {
     }
 
     pub fn clear(&self) {
-        terminal::disable_raw_mode();
+        let _ = terminal::disable_raw_mode();
         stdout().queue(terminal::Clear(terminal::ClearType::All)).unwrap();
         stdout().queue(cursor::MoveTo(0, 0)).unwrap();
         stdout().queue(cursor::Show).unwrap();
         stdout().flush().unwrap();
     }
     
+    /// Gives the terminal to another program.
+    pub fn suspend(&self) {
+        self.clear();
+    }
+
+    /// Takes the terminal back after `suspend`.
+    pub fn resume(&self) {
+        let _ = terminal::enable_raw_mode();
+        stdout().queue(terminal::Clear(terminal::ClearType::All)).unwrap();
+        stdout().queue(cursor::Hide).unwrap();
+        stdout().flush().unwrap();
+    }
+
     pub fn peek_key(&self) -> Option<KeyMap> {
         match poll(time::Duration::from_millis(5)) {
             Ok(true) => {
@@ -40,7 +53,19 @@ impl TermAdapter {
         }
     }
 
-    pub fn update(&mut self, dtlist: &ThingList<Thing>, status: &Status, height: usize, width: usize) {
+    pub fn peek_text_key(&self) -> Option<KeyMap> {
+        match poll(time::Duration::from_millis(5)) {
+            Ok(true) => {
+                match read() {
+                    Ok(ev) => KeyMap::text_from_crossterm_event(ev),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn update(&mut self, dtlist: &ThingList<Thing>, _status: &Status, height: usize, width: usize) {
         let mut stdout = stdout();
         stdout.queue(cursor::MoveTo(0, 0)).unwrap();
         let offset = dtlist.selected_index as i64 - height as i64 + 2_i64;
@@ -71,10 +96,10 @@ impl TermAdapter {
         stdout.flush().unwrap();
     }
 
-    pub fn update_status(&mut self, dtlist: &ThingList<Thing>, status: &Status, height: usize, width: usize) {
+    pub fn update_status(&mut self, _dtlist: &ThingList<Thing>, status: &Status, height: usize, width: usize) {
         let mut stdout = stdout();
         stdout.queue(cursor::MoveTo(0, (height - 1) as u16)).unwrap();
-        let mut s = status.0.clone();
+        let s = status.0.clone();
         let s = match s.char_indices().nth(width) {
             None => s,
             Some((idx, _)) => s[..idx].to_string(),
@@ -86,7 +111,7 @@ impl TermAdapter {
 
 pub fn get_term() -> TermAdapter {
     let ta = TermAdapter { term: stdout() };
-    terminal::enable_raw_mode();
+    let _ = terminal::enable_raw_mode();
     stdout().queue(terminal::Clear(terminal::ClearType::All)).unwrap();
     stdout().queue(cursor::Hide).unwrap();
     stdout().flush().unwrap();
@@ -19,6 +19,16 @@ impl TermAdapter {
         self.term.clear_on_exit(false).unwrap();
     }
     
+    /// Gives the terminal to another program.
+    pub fn suspend(&self) {
+        let _ = self.term.pause();
+    }
+
+    /// Takes the terminal back after `suspend`.
+    pub fn resume(&self) {
+        let _ = self.term.restart();
+    }
+
     pub fn peek_key(&self) -> Option<KeyMap> {
         match self.term.peek_event(time::Duration::from_millis(5)) {
             Ok(ev) => KeyMap::from_tuikit_event(ev),
@@ -26,11 +36,18 @@ impl TermAdapter {
         }
     }
 
-    pub fn update(&self, dtlist: &ThingList<Thing>, status: &Status, height: usize, width: usize) {
-        let main_win = Win::new(&dtlist);
+    pub fn peek_text_key(&self) -> Option<KeyMap> {
+        match self.term.peek_event(time::Duration::from_millis(5)) {
+            Ok(ev) => KeyMap::text_from_tuikit_event(ev),
+            _ => None,
+        }
+    }
+
+    pub fn update(&self, dtlist: &ThingList<Thing>, status: &Status, height: usize, _width: usize) {
+        let main_win = Win::new(dtlist);
         let hsplit = VSplit::default()
             .split(main_win.border(true).basis(Size::Fixed(height - 1)))
-            .split(Win::new(&status).basis(Size::Fixed(1)));
+            .split(Win::new(status).basis(Size::Fixed(1)));
 
         let _ = self.term.clear();
         let _ = self.term.draw(&hsplit);
@@ -0,0 +1,113 @@
+use std::cmp::min;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::Result;
+use crate::general::{Episode, Feed, Styled, ThingList};
+use crate::playback::Playback;
+use crate::player::PlayRequest;
+use crate::store::{read_records, write_records, Record};
+
+/// An episode waiting in the Up Next queue, kept in `upnext.xml`. Episodes are found again
+/// by feed name and `Episode::key`; the URL is enough to stream it if the feed is gone.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct QueuedEpisode {
+    pub key: String,
+    pub feed: String,
+    pub title: String,
+    pub url: String,
+}
+
+impl QueuedEpisode {
+    pub fn new(feed: &Feed, ep: &Episode) -> QueuedEpisode {
+        QueuedEpisode {
+            key: ep.key(),
+            feed: feed.name.clone(),
+            title: ep.name.clone(),
+            url: ep.enclosure.url.clone(),
+        }
+    }
+
+    pub fn episode<'a>(&self, feeds: &'a [Feed]) -> Option<&'a Episode> {
+        feeds.iter()
+            .find(|feed| feed.name == self.feed)
+            .and_then(|feed| feed.episodes.things.iter().find(|ep| ep.key() == self.key))
+    }
+
+    /// Plays the episode from where it was left, or streams the saved URL when its feed
+    /// no longer lists it.
+    pub fn play_request(&self, feeds: &[Feed]) -> PlayRequest {
+        match self.episode(feeds) {
+            Some(ep) => PlayRequest::new(ep, ep.resume_position()),
+            None => PlayRequest {
+                key: self.key.clone(),
+                file: self.url.clone(),
+                url: self.url.clone(),
+                title: self.title.clone(),
+                start: Duration::from_secs(0),
+            },
+        }
+    }
+}
+
+impl Display for QueuedEpisode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.feed, self.title)
+    }
+}
+
+impl Styled for QueuedEpisode {}
+
+impl ThingList<QueuedEpisode> {
+    /// Adds an episode at `index`, or at the end of the queue. An episode that is already
+    /// queued is moved instead of added twice.
+    pub fn add(&mut self, queued: QueuedEpisode, index: Option<usize>) {
+        self.things.retain(|q| q.key != queued.key);
+        match index {
+            Some(index) => self.things.insert(min(index, self.things.len()), queued),
+            None => self.things.push(queued),
+        }
+        self.selected_index = min(self.selected_index, self.things.len() - 1);
+    }
+
+    pub fn remove_current(&mut self) {
+        if self.things.is_empty() {
+            return;
+        }
+        self.things.remove(self.selected_index);
+        self.selected_index = min(self.selected_index, self.things.len().saturating_sub(1));
+    }
+
+    /// Drops every episode that has been played; returns true if any were removed.
+    pub fn remove_played(&mut self, playback: &Playback) -> bool {
+        let before = self.things.len();
+        self.things.retain(|q| !playback.get(&q.key).is_some_and(|p| p.played));
+        self.selected_index = min(self.selected_index, self.things.len().saturating_sub(1));
+        self.things.len() != before
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SavedUpNext {
+    #[serde(rename = "episode", default)]
+    episodes: Vec<QueuedEpisode>,
+}
+
+pub fn load_up_next(config_path: &Path) -> Result<Vec<QueuedEpisode>> {
+    Ok(read_records::<SavedUpNext>(&config_path.join("upnext.xml"))?.episodes)
+}
+
+pub fn save_up_next(config_path: &Path, queue: &[QueuedEpisode]) -> Result<()> {
+    let records: Vec<Record> = queue.iter()
+        .map(|q| vec![
+            ("key", q.key.clone()),
+            ("feed", q.feed.clone()),
+            ("title", q.title.clone()),
+            ("url", q.url.clone()),
+        ])
+        .collect();
+    write_records(&config_path.join("upnext.xml"), "upnext", "episode", &records)
+}